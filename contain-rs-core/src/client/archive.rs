@@ -0,0 +1,191 @@
+//!
+//! Tar archives are what runtimes use to transfer files into and out of containers.
+//!
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use tar::{Archive, Builder, EntryType, Header};
+
+use crate::{
+    container::{ContainerFile, FileSource},
+    error::ContainerResult,
+};
+
+///
+/// Builds an archive that places all files at their container path when extracted at `/`.
+///
+pub(crate) fn archive_files(files: &[ContainerFile]) -> ContainerResult<Vec<u8>> {
+    let mut builder = Builder::new(Vec::new());
+
+    for file in files {
+        let path = file.container_path.trim_start_matches('/');
+
+        append(&mut builder, &file.source, path)?;
+    }
+
+    Ok(builder.into_inner()?)
+}
+
+///
+/// Builds an archive containing a single host file or directory under the given name.
+///
+pub(crate) fn archive_path(host_path: &Path, name: &str) -> ContainerResult<Vec<u8>> {
+    let mut builder = Builder::new(Vec::new());
+
+    append(
+        &mut builder,
+        &FileSource::Path(host_path.to_path_buf()),
+        name,
+    )?;
+
+    Ok(builder.into_inner()?)
+}
+
+///
+/// Builds an archive with the content of a directory at its root.
+///
+pub(crate) fn archive_dir(dir: &Path) -> ContainerResult<Vec<u8>> {
+    let mut builder = Builder::new(Vec::new());
+
+    builder.append_dir_all(".", dir)?;
+
+    Ok(builder.into_inner()?)
+}
+
+///
+/// Unpacks a whole archive into a directory.
+///
+pub(crate) fn unpack(archive: &[u8], dir: &Path) -> ContainerResult<()> {
+    Archive::new(archive).unpack(dir)?;
+
+    Ok(())
+}
+
+fn append(builder: &mut Builder<Vec<u8>>, source: &FileSource, path: &str) -> io::Result<()> {
+    match source {
+        FileSource::Path(host_path) => {
+            if host_path.is_dir() {
+                builder.append_dir_all(path, host_path)
+            } else {
+                builder.append_path_with_name(host_path, path)
+            }
+        }
+        FileSource::Content(content) => {
+            let mut header = Header::new_gnu();
+
+            header.set_entry_type(EntryType::Regular);
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+
+            builder.append_data(&mut header, path, content.as_slice())
+        }
+    }
+}
+
+///
+/// Extracts an archive holding a single file or directory, so that it ends up at `host_path`.
+///
+/// Runtimes name the top level entry after the copied path, which is replaced by `host_path`.
+/// Symlinks of the archive are extracted as they are, but nothing is written through them,
+/// so an archive can not place files outside of `host_path`.
+///
+pub(crate) fn extract_to(archive: impl Read, host_path: &Path) -> ContainerResult<()> {
+    let mut archive = Archive::new(archive);
+    // a symlink chosen as destination is followed, like `docker cp` does
+    let host_path_linked = is_symlink(host_path);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let relative = relative_path(&entry.path()?)?;
+
+        check_parents(host_path, &relative, host_path_linked)?;
+
+        let target = if relative.as_os_str().is_empty() {
+            host_path.to_path_buf()
+        } else {
+            host_path.join(&relative)
+        };
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // hard links name another entry of the archive, which is placed below `host_path` as well
+        if entry.header().entry_type() == EntryType::Link {
+            let link = match entry.link_name()? {
+                Some(link_name) => relative_path(&link_name)?,
+                None => return Err(refuse(&target).into()),
+            };
+
+            check_parents(host_path, &link, host_path_linked)?;
+
+            fs::hard_link(host_path.join(link), &target)?;
+        } else {
+            entry.unpack(&target)?;
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// The path of an archive entry relative to `host_path`, without its top level entry.
+///
+fn relative_path(path: &Path) -> io::Result<PathBuf> {
+    if path
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(refuse(path));
+    }
+
+    Ok(path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .skip(1)
+        .collect())
+}
+
+///
+/// Fails when a directory, an entry below `host_path` would be written to, is a symlink.
+///
+/// Earlier entries of the archive might have placed the symlink, pointing anywhere on the host.
+///
+fn check_parents(host_path: &Path, relative: &Path, host_path_linked: bool) -> io::Result<()> {
+    let mut parent = host_path.to_path_buf();
+    let mut components = relative.components().peekable();
+
+    if components.peek().is_some() && !host_path_linked && is_symlink(&parent) {
+        return Err(refuse(&host_path.join(relative)));
+    }
+
+    while let Some(component) = components.next() {
+        if components.peek().is_none() {
+            break;
+        }
+
+        parent.push(component);
+
+        if is_symlink(&parent) {
+            return Err(refuse(&host_path.join(relative)));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+fn refuse(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Refusing to extract {}", path.display()),
+    )
+}
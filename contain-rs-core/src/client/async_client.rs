@@ -0,0 +1,580 @@
+//!
+//! Async counterparts of [Client](super::Client) and [Handle](super::Handle) built on tokio.
+//!
+//! Commands are built with the same functions as the blocking clients (see [super::shared]),
+//! but are awaited instead of blocking the calling thread.
+//!
+//! This module is only available with the `async` feature enabled.
+//!
+//! ```no_run
+//! use contain_rs_core::{
+//!     client::async_client::{AsyncClient, AsyncDocker, AsyncHandle},
+//!     container::{Container, Image, HealthCheck, WaitStrategy},
+//! };
+//! use std::str::FromStr;
+//!
+//! # async fn example() {
+//! let client = AsyncDocker::new();
+//!
+//! let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+//!
+//! container.health_check(HealthCheck::new("curl http://localhost || exit 1"))
+//!     .wait_for(WaitStrategy::HealthCheck);
+//!
+//! let handle = client.create(container);
+//!
+//! handle.run_and_wait().await.unwrap();
+//! handle.rm().await.unwrap();
+//! # }
+//! ```
+//!
+
+use std::{
+    future::Future,
+    process::{Command as StdCommand, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    process::{Child, Command},
+    runtime::{Handle as RuntimeHandle, RuntimeFlavor},
+    sync::mpsc,
+    task,
+};
+use tracing::*;
+
+use crate::{
+    container::{Container, Image, IntoContainer, Port, PullPolicy},
+    error::{ContainerResult, ContainersError},
+    rt::DetailedContainerInfo,
+};
+
+use super::{
+    archive::archive_files,
+    docker::Docker,
+    podman::Podman,
+    shared::{
+        build_copy_archive_command, build_create_command, build_image_inspect_command,
+        build_inspect_command, build_log_command, build_pull_command, build_rm_command,
+        build_run_command, build_start_command, build_stop_command, command_error, command_stdout,
+        parse_inspect_output, run_and_wait_for_command, spawn_error, wait_for_strategy,
+    },
+    wait::{Cancel, CancelOnDrop},
+};
+
+///
+/// The async version of [Client](super::Client).
+///
+pub trait AsyncClient: Clone + Send + Sync + Sized + 'static {
+    fn create<C: IntoContainer>(&self, container: C) -> AsyncContainerHandle<Self>;
+    fn run(&self, container: &Container) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn stop(&self, container: &Container) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn rm(&self, container: &Container) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn log(
+        &self,
+        container: &Container,
+    ) -> impl Future<Output = ContainerResult<Option<AsyncLog>>> + Send;
+    fn inspect(
+        &self,
+        container: &Container,
+    ) -> impl Future<Output = ContainerResult<Option<DetailedContainerInfo>>> + Send;
+    fn exists(&self, container: &Container) -> impl Future<Output = ContainerResult<bool>> + Send;
+    fn runs(&self, container: &Container) -> impl Future<Output = ContainerResult<bool>> + Send;
+    fn wait(&self, container: &Container) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn pull(&self, image: &Image) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn image_exists(&self, image: &Image) -> impl Future<Output = ContainerResult<bool>> + Send;
+
+    ///
+    /// Removes the container without awaiting anything.
+    ///
+    /// This is used when an [AsyncContainerHandle] is dropped without being removed explicitly.
+    ///
+    fn rm_blocking(&self, container: &Container) -> ContainerResult<()>;
+}
+
+///
+/// The async version of [Handle](super::Handle).
+///
+pub trait AsyncHandle {
+    fn run(&self) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn wait(&self) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn run_and_wait(&self) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn stop(&self) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn rm(&self) -> impl Future<Output = ContainerResult<()>> + Send;
+    fn log(&self) -> impl Future<Output = ContainerResult<Option<AsyncLog>>> + Send;
+    fn container(&self) -> &Container;
+    fn is_running(&self) -> impl Future<Output = ContainerResult<bool>> + Send;
+    fn exists(&self) -> impl Future<Output = ContainerResult<bool>> + Send;
+    fn host_port(&self, port: Port) -> impl Future<Output = ContainerResult<Option<u16>>> + Send;
+}
+
+///
+/// The output of a container, stdout and stderr are merged line by line.
+///
+/// The underlying log process is killed when this is dropped.
+///
+pub struct AsyncLog {
+    lines: mpsc::UnboundedReceiver<std::io::Result<String>>,
+    _child: Child,
+}
+
+impl AsyncLog {
+    pub async fn next_line(&mut self) -> ContainerResult<Option<String>> {
+        match self.lines.recv().await {
+            Some(line) => Ok(Some(line?)),
+            None => Ok(None),
+        }
+    }
+}
+
+///
+/// An async handle to a container.
+///
+/// Prefer removing the container explicitly with [AsyncHandle::rm].
+/// When a container run through the handle is dropped before that, it is removed in a blocking fashion,
+/// using [tokio::task::block_in_place] on multi threaded runtimes.
+///
+pub struct AsyncContainerHandle<T: AsyncClient> {
+    client: T,
+    container: Container,
+    cleanup_pending: AtomicBool,
+}
+
+impl<T: AsyncClient> AsyncHandle for AsyncContainerHandle<T> {
+    async fn run(&self) -> ContainerResult<()> {
+        if !self.is_running().await? {
+            self.client.run(&self.container).await?;
+            self.cleanup_pending.store(true, Ordering::SeqCst);
+        }
+
+        Ok(())
+    }
+
+    async fn wait(&self) -> ContainerResult<()> {
+        self.client.wait(&self.container).await
+    }
+
+    async fn run_and_wait(&self) -> ContainerResult<()> {
+        if !self.is_running().await? {
+            self.run().await?;
+            self.wait().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn stop(&self) -> ContainerResult<()> {
+        if self.is_running().await? {
+            self.client.stop(&self.container).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn rm(&self) -> ContainerResult<()> {
+        self.stop().await?;
+
+        if self.exists().await? {
+            self.client.rm(&self.container).await?;
+        }
+
+        self.cleanup_pending.store(false, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    async fn log(&self) -> ContainerResult<Option<AsyncLog>> {
+        self.client.log(&self.container).await
+    }
+
+    fn container(&self) -> &Container {
+        &self.container
+    }
+
+    async fn is_running(&self) -> ContainerResult<bool> {
+        Ok(self.exists().await? && self.client.runs(&self.container).await?)
+    }
+
+    async fn exists(&self) -> ContainerResult<bool> {
+        self.client.exists(&self.container).await
+    }
+
+    async fn host_port(&self, port: Port) -> ContainerResult<Option<u16>> {
+        match self.client.inspect(&self.container).await? {
+            Some(info) => Ok(info.host_port(port)),
+            None => Err(ContainersError::ContainerNotExists {
+                container_name: self.container.name.clone(),
+            }),
+        }
+    }
+}
+
+impl<T: AsyncClient> Drop for AsyncContainerHandle<T> {
+    fn drop(&mut self) {
+        if !self.cleanup_pending.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let rm = || self.client.rm_blocking(&self.container);
+
+        let result = match RuntimeHandle::try_current() {
+            Ok(runtime) if runtime.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(rm)
+            }
+            _ => rm(),
+        };
+
+        if let Err(e) = result {
+            warn!(%e, container = %self.container.name, "Failed to remove container");
+        }
+    }
+}
+
+///
+/// The async counterpart of [Docker](super::docker::Docker).
+///
+#[derive(Clone, Default)]
+pub struct AsyncDocker {
+    blocking: Docker,
+}
+
+impl AsyncDocker {
+    const BINARY: &'static str = "docker";
+
+    pub fn new() -> Self {
+        Self {
+            blocking: Docker::new(),
+        }
+    }
+}
+
+///
+/// The async counterpart of [Podman](super::podman::Podman).
+///
+#[derive(Clone, Default)]
+pub struct AsyncPodman {
+    blocking: Podman,
+}
+
+impl AsyncPodman {
+    const BINARY: &'static str = "podman";
+
+    pub fn new() -> Self {
+        Self {
+            blocking: Podman::new(),
+        }
+    }
+}
+
+///
+/// Implements [AsyncClient] for a CLI based client.
+///
+/// Waiting is delegated to the blocking client on a blocking thread,
+/// so both clients evaluate [wait strategies](crate::container::WaitStrategy) the same way.
+/// Dropping the future cancels waiting on the blocking thread as well.
+///
+macro_rules! impl_async_cli_client {
+    ($client:ty) => {
+        impl AsyncClient for $client {
+            fn create<C: IntoContainer>(&self, container: C) -> AsyncContainerHandle<Self> {
+                AsyncContainerHandle {
+                    client: self.clone(),
+                    container: container.into_container(),
+                    cleanup_pending: AtomicBool::new(false),
+                }
+            }
+
+            async fn run(&self, container: &Container) -> ContainerResult<()> {
+                ensure_image(self, container).await?;
+
+                if !container.files.is_empty() {
+                    return run_with_files(Self::BINARY, container).await;
+                }
+
+                let mut command = StdCommand::new(Self::BINARY);
+
+                build_run_command(&mut command, container);
+                run_command(command).await?;
+
+                Ok(())
+            }
+
+            async fn stop(&self, container: &Container) -> ContainerResult<()> {
+                let mut command = StdCommand::new(Self::BINARY);
+
+                build_stop_command(&mut command, container);
+                run_command(command).await?;
+
+                Ok(())
+            }
+
+            async fn rm(&self, container: &Container) -> ContainerResult<()> {
+                let mut command = StdCommand::new(Self::BINARY);
+
+                build_rm_command(&mut command, container);
+                run_command(command).await?;
+
+                Ok(())
+            }
+
+            async fn log(&self, container: &Container) -> ContainerResult<Option<AsyncLog>> {
+                if self.exists(container).await? {
+                    let mut command = StdCommand::new(Self::BINARY);
+
+                    build_log_command(&mut command, container);
+
+                    Ok(Some(do_log(command)?))
+                } else {
+                    Ok(None)
+                }
+            }
+
+            async fn inspect(
+                &self,
+                container: &Container,
+            ) -> ContainerResult<Option<DetailedContainerInfo>> {
+                let mut command = StdCommand::new(Self::BINARY);
+
+                build_inspect_command(&mut command, container);
+
+                let mut command = Command::from(command);
+                let output = output(&mut command).await?;
+
+                parse_inspect_output(command.as_std(), output)
+            }
+
+            async fn exists(&self, container: &Container) -> ContainerResult<bool> {
+                Ok(self.inspect(container).await?.is_some())
+            }
+
+            async fn runs(&self, container: &Container) -> ContainerResult<bool> {
+                match self.inspect(container).await? {
+                    Some(detail) => Ok(detail.state.running),
+                    None => Ok(false),
+                }
+            }
+
+            async fn wait(&self, container: &Container) -> ContainerResult<()> {
+                let (client, container) = (self.blocking.clone(), container.clone());
+                let additional_wait_period = container.additional_wait_period;
+                let cancel = Cancel::default();
+                let _cancel_on_drop = CancelOnDrop(cancel.clone());
+
+                let result =
+                    task::spawn_blocking(move || wait_for_strategy(&client, &container, &cancel))
+                        .await
+                        .map_err(std::io::Error::other)?;
+
+                tokio::time::sleep(additional_wait_period).await;
+
+                result
+            }
+
+            async fn pull(&self, image: &Image) -> ContainerResult<()> {
+                let mut command = StdCommand::new(Self::BINARY);
+
+                build_pull_command(&mut command, image);
+
+                pull(command).instrument(info_span!("pull", %image)).await
+            }
+
+            async fn image_exists(&self, image: &Image) -> ContainerResult<bool> {
+                let mut command = StdCommand::new(Self::BINARY);
+
+                build_image_inspect_command(&mut command, image);
+
+                let mut command = Command::from(command);
+                let output = output(&mut command).await?;
+
+                if output.status.success() {
+                    return Ok(true);
+                }
+
+                match command_error(command.as_std(), output) {
+                    ContainersError::ImageNotFound { .. } => Ok(false),
+                    e => Err(e),
+                }
+            }
+
+            fn rm_blocking(&self, container: &Container) -> ContainerResult<()> {
+                let mut command = StdCommand::new(Self::BINARY);
+
+                build_rm_command(&mut command, container);
+                run_and_wait_for_command(&mut command)?;
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_async_cli_client!(AsyncDocker);
+impl_async_cli_client!(AsyncPodman);
+
+#[instrument(skip_all)]
+async fn output(command: &mut Command) -> ContainerResult<std::process::Output> {
+    debug!(?command, "Running command");
+
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| spawn_error(command.as_std(), e))
+}
+
+async fn run_command(command: StdCommand) -> ContainerResult<String> {
+    let mut command = Command::from(command);
+    let output = output(&mut command).await?;
+
+    command_stdout(command.as_std(), output)
+}
+
+///
+/// The async version of [super::shared::pull].
+///
+async fn pull(command: StdCommand) -> ContainerResult<()> {
+    debug!(?command, "Running command");
+
+    let mut command = Command::from(command);
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(command.as_std(), e))?;
+
+    let (stdout, stderr) = tokio::join!(
+        report_progress(child.stdout.take()),
+        report_progress(child.stderr.take())
+    );
+
+    let output = std::process::Output {
+        status: child.wait().await?,
+        stdout: stdout?,
+        stderr: stderr?,
+    };
+
+    command_stdout(command.as_std(), output)?;
+
+    Ok(())
+}
+
+async fn report_progress(stream: Option<impl AsyncRead + Unpin>) -> ContainerResult<Vec<u8>> {
+    let mut output = Vec::new();
+
+    if let Some(stream) = stream {
+        let mut lines = BufReader::new(stream).lines();
+
+        while let Some(line) = lines.next_line().await? {
+            info!(progress = line.trim(), "Pulling image");
+
+            output.extend_from_slice(line.as_bytes());
+            output.push(b'\n');
+        }
+    }
+
+    Ok(output)
+}
+
+///
+/// The async version of [super::shared::ensure_image].
+///
+async fn ensure_image<C: AsyncClient>(client: &C, container: &Container) -> ContainerResult<()> {
+    let image = &container.image;
+
+    match container.pull_policy {
+        PullPolicy::Always => client.pull(image).await,
+        PullPolicy::IfNotPresent if client.image_exists(image).await? => Ok(()),
+        PullPolicy::IfNotPresent => client.pull(image).await,
+        PullPolicy::Never if client.image_exists(image).await? => Ok(()),
+        PullPolicy::Never => Err(ContainersError::ImageNotFound {
+            message: format!("{image} is not present and the pull policy is never"),
+        }),
+    }
+}
+
+///
+/// The async version of [super::shared::run_with_files].
+///
+async fn run_with_files(binary: &str, container: &Container) -> ContainerResult<()> {
+    let archive = archive_files(&container.files)?;
+
+    let mut create = StdCommand::new(binary);
+    build_create_command(&mut create, container);
+    run_command(create).await?;
+
+    let mut copy = StdCommand::new(binary);
+    build_copy_archive_command(&mut copy, container);
+    debug!(?copy, "Running command");
+
+    let mut copy = Command::from(copy);
+    let mut child = copy
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(copy.as_std(), e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // a failing command may close stdin early, its exit code tells what went wrong
+        if let Err(e) = stdin.write_all(&archive).await {
+            debug!(%e, "Failed writing to stdin");
+        }
+    }
+
+    command_stdout(copy.as_std(), child.wait_with_output().await?)?;
+
+    let mut start = StdCommand::new(binary);
+    build_start_command(&mut start, container);
+    run_command(start).await?;
+
+    Ok(())
+}
+
+fn do_log(command: StdCommand) -> ContainerResult<AsyncLog> {
+    let mut command = Command::from(command);
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| spawn_error(command.as_std(), e))?;
+
+    let (sender, lines) = mpsc::unbounded_channel();
+
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_lines(stdout, sender.clone()));
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_lines(stderr, sender));
+    }
+
+    Ok(AsyncLog {
+        lines,
+        _child: child,
+    })
+}
+
+async fn forward_lines(
+    stream: impl AsyncRead + Unpin,
+    sender: mpsc::UnboundedSender<std::io::Result<String>>,
+) {
+    let mut lines = BufReader::new(stream).lines();
+
+    loop {
+        let line = lines.next_line().await.transpose();
+
+        match line {
+            Some(line) => {
+                if sender.send(line).is_err() {
+                    return;
+                }
+            }
+            None => return,
+        }
+    }
+}
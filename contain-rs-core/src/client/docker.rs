@@ -1,17 +1,27 @@
-use std::process::Command;
+use std::{path::Path, process::Command, str::FromStr};
+
+use lazy_static::lazy_static;
+use tracing::*;
 
 use crate::{
-    container::{Container, IntoContainer},
+    container::{Container, Exec, Image, ImageBuild, IntoContainer, Network},
     error::ContainerResult,
-    rt::DetailedContainerInfo,
+    rt::{DetailedContainerInfo, ExecOutput},
 };
 
 use super::{
+    docker_api::DockerApi,
+    http::Endpoint,
+    reaper,
     shared::{
-        build_rm_command, build_run_command, build_stop_command, do_log, inspect,
-        run_and_wait_for_command, wait_for,
+        self, build_copy_from_command, build_copy_to_command, build_image, build_kill_command,
+        build_network_connect_command, build_network_create_command, build_network_rm_command,
+        build_pause_command, build_restart_command, build_rm_command, build_run_command,
+        build_stop_command, build_unpause_command, do_log, ensure_image, ensure_volumes,
+        image_digest, image_exists, inspect, network_exists, run_and_wait_for_command,
+        run_with_files, wait_for,
     },
-    Client, ContainerHandle, Log,
+    Client, ContainerHandle, EventFilter, Events, ExecStream, Log, NetworkHandle, Pruned,
 };
 
 ///
@@ -36,15 +46,17 @@ use super::{
 /// client.rm(&container).unwrap();
 /// ```
 ///
-#[allow(dead_code)]
 #[derive(Clone)]
-pub struct Docker {
-    host: Option<String>,
+pub struct Docker;
+
+lazy_static! {
+    /// The endpoint of the active docker context, which is the daemon the cli talks to.
+    static ref CONTEXT_ENDPOINT: Endpoint = context_endpoint();
 }
 
 impl Docker {
     pub fn new() -> Self {
-        Self { host: None }
+        Self
     }
 
     fn build_command(&self) -> Command {
@@ -52,6 +64,31 @@ impl Docker {
     }
 }
 
+///
+/// Resolves the endpoint of the active docker context, which also takes `DOCKER_HOST` and `DOCKER_CONTEXT` into account.
+///
+/// Falls back to the endpoint of [DockerApi::new] when the cli can not tell, or the context uses an endpoint
+/// other than a unix socket or tcp, like ssh.
+///
+fn context_endpoint() -> Endpoint {
+    let mut cmd = Command::new("docker");
+
+    cmd.args([
+        "context",
+        "inspect",
+        "--format",
+        "{{.Endpoints.docker.Host}}",
+    ]);
+
+    match run_and_wait_for_command(&mut cmd).and_then(|host| Endpoint::from_str(host.trim())) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            warn!(%e, "Could not resolve the endpoint of the docker context");
+            DockerApi::new().endpoint().clone()
+        }
+    }
+}
+
 impl Default for Docker {
     fn default() -> Self {
         Self::new()
@@ -66,13 +103,17 @@ impl Client for Docker {
     }
 
     fn create<C: IntoContainer>(&self, container: C) -> super::ContainerHandle<Self::ClientType> {
-        ContainerHandle {
-            client: self.clone(),
-            container: container.into_container(),
-        }
+        ContainerHandle::new(self.clone(), container.into_container())
     }
 
     fn run(&self, container: &Container) -> ContainerResult<()> {
+        ensure_image(self, container)?;
+        ensure_volumes(|| self.build_command(), container)?;
+
+        if !container.files.is_empty() {
+            return run_with_files(|| self.build_command(), container);
+        }
+
         let mut cmd = self.build_command();
 
         build_run_command(&mut cmd, container);
@@ -90,6 +131,42 @@ impl Client for Docker {
         Ok(())
     }
 
+    fn pause(&self, container: &Container) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_pause_command(&mut cmd, container);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn unpause(&self, container: &Container) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_unpause_command(&mut cmd, container);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn restart(&self, container: &Container) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_restart_command(&mut cmd, container);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn kill(&self, container: &Container, signal: &str) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_kill_command(&mut cmd, container, signal);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
     fn rm(&self, container: &Container) -> ContainerResult<()> {
         let mut cmd = self.build_command();
 
@@ -100,7 +177,7 @@ impl Client for Docker {
     }
 
     fn log(&self, container: &Container) -> ContainerResult<Option<Log>> {
-        if self.runs(container)? {
+        if self.exists(container)? {
             Ok(Some(do_log(self, container)?))
         } else {
             Ok(None)
@@ -108,7 +185,14 @@ impl Client for Docker {
     }
 
     fn inspect(&self, container: &Container) -> ContainerResult<Option<DetailedContainerInfo>> {
-        inspect(self, container)
+        let Some(mut info) = inspect(self, container)? else {
+            return Ok(None);
+        };
+
+        // docker only reports the digest with the image
+        info.image_digest = image_digest(&mut self.build_command(), &info.image_id)?;
+
+        Ok(Some(info))
     }
 
     fn exists(&self, container: &Container) -> ContainerResult<bool> {
@@ -125,4 +209,111 @@ impl Client for Docker {
     fn wait(&self, container: &Container) -> ContainerResult<()> {
         wait_for(self, container)
     }
+
+    fn pull(&self, image: &Image) -> ContainerResult<()> {
+        shared::pull(&mut self.build_command(), image)
+    }
+
+    fn image_exists(&self, image: &Image) -> ContainerResult<bool> {
+        image_exists(&mut self.build_command(), image)
+    }
+
+    fn build_image<F: FnMut(&str)>(&self, build: &ImageBuild, output: F) -> ContainerResult<Image> {
+        build_image(&mut self.build_command(), build, output)
+    }
+
+    fn copy_to(
+        &self,
+        container: &Container,
+        host_path: &Path,
+        container_path: &str,
+    ) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_copy_to_command(&mut cmd, container, host_path, container_path);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn copy_from(
+        &self,
+        container: &Container,
+        container_path: &str,
+        host_path: &Path,
+    ) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_copy_from_command(&mut cmd, container, container_path, host_path);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn exec(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecOutput> {
+        shared::exec(&mut self.build_command(), container, exec)
+    }
+
+    fn exec_stream(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecStream> {
+        shared::exec_stream(&mut self.build_command(), container, exec)
+    }
+
+    fn events(&self, filter: &EventFilter) -> ContainerResult<Events> {
+        shared::events(self.build_command(), filter)
+    }
+
+    fn network(&self, network: Network) -> NetworkHandle<Self::ClientType> {
+        NetworkHandle {
+            client: self.clone(),
+            network,
+        }
+    }
+
+    fn create_network(&self, network: &Network) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_network_create_command(&mut cmd, network);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn rm_network(&self, network: &Network) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_network_rm_command(&mut cmd, network);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn network_exists(&self, network: &Network) -> ContainerResult<bool> {
+        network_exists(&mut self.build_command(), network)
+    }
+
+    fn connect_network(
+        &self,
+        network: &Network,
+        container: &Container,
+        aliases: &[String],
+    ) -> ContainerResult<()> {
+        let mut cmd = self.build_command();
+
+        build_network_connect_command(&mut cmd, network, container, aliases);
+        run_and_wait_for_command(&mut cmd)?;
+
+        Ok(())
+    }
+
+    fn prune_orphans(&self) -> ContainerResult<Pruned> {
+        shared::prune_orphans(|| self.build_command())
+    }
+
+    fn start_reaper(&self) -> ContainerResult<()> {
+        reaper::ensure_started(self, reaper::socket(&CONTEXT_ENDPOINT))
+    }
+
+    fn host(&self) -> String {
+        CONTEXT_ENDPOINT.host().to_string()
+    }
 }
@@ -0,0 +1,1213 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    env,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+    process::Command,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use base64::prelude::*;
+use serde::Deserialize;
+
+use serde_json::{json, Value};
+use tracing::*;
+
+use crate::{
+    container::{
+        BuildContext, Container, Exec, Image, ImageBuild, IntoContainer, Label, Network, Volume,
+    },
+    error::{ContainerResult, ContainersError},
+    rt::{DetailedContainerInfo, ExecOutput, ImageInfo, NetworkInfo},
+    session::{is_orphan, with_session_labels, PID_LABEL},
+};
+
+use super::{
+    archive::{archive_dir, archive_files, archive_path, extract_to},
+    http::{encode, Connection, Endpoint},
+    reaper,
+    shared::{daemon_error, ensure_image, named_volumes, wait_for},
+    Client, ContainerHandle, EventFilter, Events, ExecStream, Log, NetworkHandle, Pruned,
+};
+
+///
+/// The DockerApi struct is used for accessing the docker engine api directly, without the need for a docker cli.
+///
+/// By default the api is expected on `DOCKER_HOST`, falling back to `unix:///var/run/docker.sock`.
+/// Only plain http is supported for tcp endpoints.
+///
+/// ```no_run
+/// use contain_rs_core::{
+///     client::{docker_api::DockerApi, Client, Handle},
+///     container::{Container, Image, HealthCheck, WaitStrategy},
+/// };
+/// use std::str::FromStr;
+///
+/// let client = DockerApi::new();
+///
+/// let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+///
+/// container.health_check(HealthCheck::new("curl http://localhost || exit 1"))
+///     .wait_for(WaitStrategy::HealthCheck);
+///
+/// client.run(&container).unwrap();
+/// client.wait(&container).unwrap();
+/// client.rm(&container).unwrap();
+/// ```
+///
+#[derive(Clone)]
+pub struct DockerApi {
+    endpoint: Endpoint,
+}
+
+impl DockerApi {
+    const DEFAULT_HOST: &'static str = "unix:///var/run/docker.sock";
+
+    pub fn new() -> Self {
+        let host = env::var("DOCKER_HOST").ok();
+
+        let endpoint = match host.as_deref().map(Endpoint::from_str) {
+            Some(Ok(endpoint)) => endpoint,
+            Some(Err(e)) => {
+                warn!(%e, "Ignoring DOCKER_HOST");
+                Endpoint::from_str(Self::DEFAULT_HOST).unwrap()
+            }
+            None => Endpoint::from_str(Self::DEFAULT_HOST).unwrap(),
+        };
+
+        Self::from_endpoint(endpoint)
+    }
+
+    pub fn from_endpoint(endpoint: Endpoint) -> Self {
+        Self { endpoint }
+    }
+
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    fn create_container(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &format!("/containers/create?name={}", encode(&container.name)),
+                Some(&build_create_body(container)),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    ///
+    /// Creates the named volumes of a container that do not exist yet, carrying the session labels.
+    ///
+    fn create_volumes(&self, container: &Container) -> ContainerResult<()> {
+        for name in named_volumes(container) {
+            let response =
+                self.endpoint
+                    .request("GET", &format!("/volumes/{}", encode(name)), None)?;
+
+            if response.status != 404 {
+                response.error_for_status()?;
+                continue;
+            }
+
+            self.endpoint
+                .request(
+                    "POST",
+                    "/volumes/create",
+                    Some(&json!({ "Name": name, "Labels": label_map(&[]) })),
+                )?
+                .error_for_status()?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// The manifest digest of an image, none if the image is gone or was never in a registry.
+    ///
+    fn image_digest(&self, image_id: &str) -> ContainerResult<Option<String>> {
+        if image_id.is_empty() {
+            return Ok(None);
+        }
+
+        let response =
+            self.endpoint
+                .request("GET", &format!("/images/{}/json", encode(image_id)), None)?;
+
+        match response.status {
+            404 => Ok(None),
+            _ => {
+                let info: ImageInfo = response.error_for_status()?.json()?;
+
+                Ok(info.digest().map(ToString::to_string))
+            }
+        }
+    }
+}
+
+impl Default for DockerApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client for DockerApi {
+    type ClientType = DockerApi;
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("docker");
+
+        command.arg("--host").arg(self.endpoint.to_string());
+        command
+    }
+
+    fn create<C: IntoContainer>(&self, container: C) -> ContainerHandle<Self::ClientType> {
+        ContainerHandle::new(self.clone(), container.into_container())
+    }
+
+    fn run(&self, container: &Container) -> ContainerResult<()> {
+        ensure_image(self, container)?;
+        self.create_volumes(container)?;
+        self.create_container(container)?;
+
+        if !container.files.is_empty() {
+            put_archive(
+                &self.endpoint,
+                "",
+                container,
+                "/",
+                &archive_files(&container.files)?,
+            )?;
+        }
+
+        self.endpoint
+            .request(
+                "POST",
+                &format!("/containers/{}/start", encode(&container.name)),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn stop(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request_within(
+                "POST",
+                &format!(
+                    "/containers/{}/stop{}",
+                    encode(&container.name),
+                    stop_timeout_query(container, "t")
+                ),
+                None,
+                container.stop_timeout.unwrap_or_default(),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn pause(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &format!("/containers/{}/pause", encode(&container.name)),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn unpause(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &format!("/containers/{}/unpause", encode(&container.name)),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn restart(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request_within(
+                "POST",
+                &format!(
+                    "/containers/{}/restart{}",
+                    encode(&container.name),
+                    stop_timeout_query(container, "t")
+                ),
+                None,
+                container.stop_timeout.unwrap_or_default(),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn kill(&self, container: &Container, signal: &str) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &format!(
+                    "/containers/{}/kill?signal={}",
+                    encode(&container.name),
+                    encode(signal)
+                ),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn rm(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "DELETE",
+                &format!("/containers/{}?force=true", encode(&container.name)),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn log(&self, container: &Container) -> ContainerResult<Option<Log>> {
+        if self.exists(container)? {
+            let (response, connection) = self.endpoint.stream(
+                "GET",
+                &format!(
+                    "/containers/{}/logs?follow=true&stdout=true&stderr=true",
+                    encode(&container.name)
+                ),
+                None,
+            )?;
+
+            Ok(Some(pipe_log(
+                response.error_for_status()?.into_reader(),
+                connection,
+            )?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn inspect(&self, container: &Container) -> ContainerResult<Option<DetailedContainerInfo>> {
+        let response = self.endpoint.request(
+            "GET",
+            &format!("/containers/{}/json", encode(&container.name)),
+            None,
+        )?;
+
+        match response.status {
+            404 => Ok(None),
+            _ => {
+                let mut info: DetailedContainerInfo = response.error_for_status()?.json()?;
+
+                // docker only reports the digest with the image
+                info.image_digest = self.image_digest(&info.image_id)?;
+
+                debug!(?info, "Inspect container");
+
+                Ok(Some(info))
+            }
+        }
+    }
+
+    fn exists(&self, container: &Container) -> ContainerResult<bool> {
+        Ok(self.inspect(container)?.is_some())
+    }
+
+    fn runs(&self, container: &Container) -> ContainerResult<bool> {
+        match self.inspect(container)? {
+            Some(detail) => Ok(detail.state.running),
+            None => Ok(false),
+        }
+    }
+
+    fn wait(&self, container: &Container) -> ContainerResult<()> {
+        wait_for(self, container)
+    }
+
+    #[instrument(skip_all, fields(%image))]
+    fn pull(&self, image: &Image) -> ContainerResult<()> {
+        let path = format!(
+            "/images/create?fromImage={}&tag={}",
+            encode(&image.name),
+            encode(&image.tag)
+        );
+
+        let (response, _) = self.endpoint.stream("POST", &path, None)?;
+
+        follow_progress(response.error_for_status()?.into_reader())
+    }
+
+    fn build_image<F: FnMut(&str)>(&self, build: &ImageBuild, output: F) -> ContainerResult<Image> {
+        build_image_api(&self.endpoint, "", build, output)
+    }
+
+    fn image_exists(&self, image: &Image) -> ContainerResult<bool> {
+        image_exists_api(&self.endpoint, "", image)
+    }
+
+    fn copy_to(
+        &self,
+        container: &Container,
+        host_path: &Path,
+        container_path: &str,
+    ) -> ContainerResult<()> {
+        copy_to_api(&self.endpoint, "", container, host_path, container_path)
+    }
+
+    fn copy_from(
+        &self,
+        container: &Container,
+        container_path: &str,
+        host_path: &Path,
+    ) -> ContainerResult<()> {
+        copy_from_api(&self.endpoint, "", container, container_path, host_path)
+    }
+
+    fn exec(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecOutput> {
+        exec_api(&self.endpoint, "", container, exec)
+    }
+
+    fn exec_stream(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecStream> {
+        exec_stream_api(&self.endpoint, "", container, exec)
+    }
+
+    fn events(&self, filter: &EventFilter) -> ContainerResult<Events> {
+        events_api(&self.endpoint, "", filter)
+    }
+
+    fn network(&self, network: Network) -> NetworkHandle<Self::ClientType> {
+        NetworkHandle {
+            client: self.clone(),
+            network,
+        }
+    }
+
+    fn create_network(&self, network: &Network) -> ContainerResult<()> {
+        let mut body = json!({
+            "Name": network.name,
+            "Internal": network.internal,
+            "Labels": label_map(&network.labels),
+        });
+
+        if let Some(driver) = &network.driver {
+            body["Driver"] = json!(driver);
+        }
+
+        self.endpoint
+            .request("POST", "/networks/create", Some(&body))?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn rm_network(&self, network: &Network) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "DELETE",
+                &format!("/networks/{}", encode(&network.name)),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn network_exists(&self, network: &Network) -> ContainerResult<bool> {
+        let response =
+            self.endpoint
+                .request("GET", &format!("/networks/{}", encode(&network.name)), None)?;
+
+        match response.status {
+            404 => Ok(false),
+            _ => {
+                response.error_for_status()?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn connect_network(
+        &self,
+        network: &Network,
+        container: &Container,
+        aliases: &[String],
+    ) -> ContainerResult<()> {
+        let body = json!({
+            "Container": container.name,
+            "EndpointConfig": { "Aliases": aliases },
+        });
+
+        self.endpoint
+            .request(
+                "POST",
+                &format!("/networks/{}/connect", encode(&network.name)),
+                Some(&body),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn prune_orphans(&self) -> ContainerResult<Pruned> {
+        prune_orphans_api(&self.endpoint, "", "/networks")
+    }
+
+    fn start_reaper(&self) -> ContainerResult<()> {
+        reaper::ensure_started(self, reaper::socket(&self.endpoint))
+    }
+
+    fn host(&self) -> String {
+        self.endpoint.host().to_string()
+    }
+}
+
+///
+/// Builds the body for a container create request, the api equivalent of [super::shared::build_run_command].
+///
+pub(crate) fn build_create_body(container: &Container) -> Value {
+    let mut exposed_ports = serde_json::Map::new();
+    let mut port_bindings = serde_json::Map::new();
+
+    let mappings = container
+        .port_mappings
+        .iter()
+        .map(|mapping| (&mapping.target, mapping.source.number.as_str()));
+    // an empty host port lets the runtime pick one
+    let exposed = container.exposed_ports.iter().map(|port| (port, ""));
+
+    mappings.chain(exposed).for_each(|(target, host_port)| {
+        let port = target.with_protocol();
+
+        exposed_ports.insert(port.clone(), json!({}));
+        port_bindings.insert(port, json!([{ "HostPort": host_port }]));
+    });
+
+    let binds: Vec<String> = container
+        .volumes
+        .iter()
+        .map(|volume| match volume {
+            Volume::Mount {
+                host_path,
+                mount_point,
+            } => format!("{host_path}:{mount_point}"),
+            Volume::Named { name, mount_point } => format!("{name}:{mount_point}"),
+        })
+        .collect();
+
+    let env: Vec<String> = container
+        .env_vars
+        .iter()
+        .map(|env_var| format!("{}={}", env_var.key, env_var.value))
+        .collect();
+
+    let mut host_config = json!({
+        "Binds": binds,
+        "PortBindings": port_bindings,
+        "PublishAllPorts": container.publish_all_ports,
+        "AutoRemove": container.auto_remove,
+    });
+
+    let mut body = json!({
+        "Image": String::from(&container.image),
+        "Env": env,
+        "Labels": label_map(&container.labels),
+        "ExposedPorts": exposed_ports,
+    });
+
+    if !container.command.is_empty() {
+        body["Cmd"] = json!(container.command);
+    }
+
+    if let Some(network) = &container.network {
+        host_config["NetworkMode"] = json!(network.name);
+        body["NetworkingConfig"] = json!({
+            "EndpointsConfig": {
+                &network.name: { "Aliases": container.network_aliases }
+            }
+        });
+    }
+
+    if let Some(check) = &container.health_check {
+        let mut health_check = json!({ "Test": ["CMD-SHELL", check.command] });
+
+        if let Some(start_period) = check.start_period {
+            health_check["StartPeriod"] = json!(start_period.as_nanos() as u64);
+        }
+
+        if let Some(interval) = check.interval {
+            health_check["Interval"] = json!(interval.as_nanos() as u64);
+        }
+
+        if let Some(timeout) = check.timeout {
+            health_check["Timeout"] = json!(timeout.as_nanos() as u64);
+        }
+
+        if let Some(retries) = check.retries {
+            health_check["Retries"] = json!(retries);
+        }
+
+        body["Healthcheck"] = health_check;
+    }
+
+    let resources = &container.resources;
+
+    if let Some(memory) = resources.memory {
+        host_config["Memory"] = json!(memory);
+    }
+
+    if let Some(memory_swap) = resources.memory_swap {
+        host_config["MemorySwap"] = json!(memory_swap);
+    }
+
+    if let Some(cpus) = resources.cpus {
+        host_config["NanoCpus"] = json!((cpus * 1e9).round() as u64);
+    }
+
+    if let Some(pids_limit) = resources.pids_limit {
+        host_config["PidsLimit"] = json!(pids_limit);
+    }
+
+    if !resources.ulimits.is_empty() {
+        let ulimits: Vec<Value> = resources
+            .ulimits
+            .iter()
+            .map(|ulimit| json!({ "Name": ulimit.name, "Soft": ulimit.soft, "Hard": ulimit.hard }))
+            .collect();
+
+        host_config["Ulimits"] = json!(ulimits);
+    }
+
+    body["HostConfig"] = host_config;
+
+    body
+}
+
+///
+/// The given labels and the [session labels](crate::session::session_labels) as a map, the latter take precedence.
+///
+pub(crate) fn label_map(labels: &[Label]) -> HashMap<String, String> {
+    with_session_labels(labels)
+        .into_iter()
+        .map(|label| (label.key, label.value))
+        .collect()
+}
+
+///
+/// The query passing the [stop timeout](Container::stop_timeout) of a container in seconds, empty if it has none.
+///
+pub(crate) fn stop_timeout_query(container: &Container, param: &str) -> String {
+    container
+        .stop_timeout_secs()
+        .map(|timeout| format!("?{param}={timeout}"))
+        .unwrap_or_default()
+}
+
+///
+/// Reports the progress of an image pull as tracing events.
+///
+/// Image pulls report errors as part of the progress stream, while still responding with status 200.
+///
+pub(crate) fn follow_progress(stream: Box<dyn Read + Send>) -> ContainerResult<()> {
+    for line in BufReader::new(stream).lines() {
+        let event: Value = match serde_json::from_str(&line?) {
+            Ok(event) => event,
+            Err(_) => continue,
+        };
+
+        if let Some(message) = event.get("error").and_then(Value::as_str) {
+            return Err(
+                daemon_error(message).unwrap_or_else(|| ContainersError::ApiError {
+                    status: 200,
+                    message: message.to_string(),
+                }),
+            );
+        }
+
+        // docker reports a status per layer, libpod plain output lines
+        let field = |name: &str| event.get(name).and_then(Value::as_str).map(str::trim);
+
+        if let Some(progress) = field("status").or_else(|| field("stream")) {
+            info!(
+                layer = field("id"),
+                progress,
+                detail = field("progress"),
+                "Pulling image"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+///
+/// Builds an image through the api, the context is uploaded as a tar archive.
+///
+pub(crate) fn build_image_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    build: &ImageBuild,
+    mut output: impl FnMut(&str),
+) -> ContainerResult<Image> {
+    let mut tags = build.tags_or_generated();
+
+    let archive = match &build.context {
+        BuildContext::Path(context) => Cow::Owned(archive_dir(context)?),
+        BuildContext::Archive(archive) => Cow::Borrowed(archive),
+    };
+
+    let mut query: Vec<String> = tags
+        .iter()
+        .map(|tag| format!("t={}", encode(&String::from(tag))))
+        .collect();
+
+    if let Some(dockerfile) = &build.dockerfile {
+        query.push(format!("dockerfile={}", encode(dockerfile)));
+    }
+
+    if let Some(target) = &build.target {
+        query.push(format!("target={}", encode(target)));
+    }
+
+    if !build.build_args.is_empty() {
+        let build_args: HashMap<&str, &str> = build
+            .build_args
+            .iter()
+            .map(|build_arg| (build_arg.key.as_str(), build_arg.value.as_str()))
+            .collect();
+
+        query.push(format!(
+            "buildargs={}",
+            encode(&serde_json::to_string(&build_args)?)
+        ));
+    }
+
+    if build.no_cache {
+        query.push("nocache=true".to_string());
+    }
+
+    let (response, _) = endpoint.stream(
+        "POST",
+        &format!("{prefix}/build?{}", query.join("&")),
+        Some(("application/x-tar", &archive)),
+    )?;
+
+    // like pulls, builds report errors as part of the output stream
+    for line in BufReader::new(response.error_for_status()?.into_reader()).lines() {
+        let event: Value = match serde_json::from_str(&line?) {
+            Ok(event) => event,
+            Err(_) => continue,
+        };
+
+        if let Some(message) = event.get("error").and_then(Value::as_str) {
+            return Err(
+                daemon_error(message).unwrap_or_else(|| ContainersError::ApiError {
+                    status: 200,
+                    message: message.to_string(),
+                }),
+            );
+        }
+
+        if let Some(stream) = event.get("stream").and_then(Value::as_str) {
+            stream
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .for_each(&mut output);
+        }
+    }
+
+    Ok(tags.swap_remove(0))
+}
+
+pub(crate) fn image_exists_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    image: &Image,
+) -> ContainerResult<bool> {
+    let response = endpoint.request(
+        "GET",
+        &format!("{prefix}/images/{}/json", encode(&String::from(image))),
+        None,
+    )?;
+
+    match response.status {
+        404 => Ok(false),
+        _ => {
+            response.error_for_status()?;
+            Ok(true)
+        }
+    }
+}
+
+///
+/// Copies a multiplexed log stream into pipes, so it can be consumed like the output of the cli.
+///
+/// Copying stops once the log is dropped, which closes the connection.
+///
+pub(crate) fn pipe_log(
+    stream: Box<dyn Read + Send>,
+    connection: Connection,
+) -> ContainerResult<Log> {
+    let (stdout, stdout_writer) = os_pipe::pipe()?;
+    let (stderr, stderr_writer) = os_pipe::pipe()?;
+
+    thread::spawn(move || {
+        if let Err(e) = demultiplex(stream, stdout_writer, stderr_writer) {
+            debug!(%e, "Log stream ended");
+        }
+    });
+
+    Ok(Log::new(stdout, stderr, connection)?)
+}
+
+///
+/// Strips the 8 byte frame headers the engine prepends to stdout and stderr chunks of non tty containers.
+///
+pub(crate) fn demultiplex(
+    mut stream: impl Read,
+    mut stdout: impl Write,
+    mut stderr: impl Write,
+) -> io::Result<()> {
+    let mut header = [0u8; 8];
+
+    loop {
+        match stream.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        let size = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as u64;
+        let out: &mut dyn Write = match header[0] {
+            2 => &mut stderr,
+            _ => &mut stdout,
+        };
+
+        io::copy(&mut (&mut stream).take(size), out)?;
+        out.flush()?;
+    }
+}
+
+#[derive(Deserialize)]
+struct ExecCreated {
+    #[serde(alias = "Id")]
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ExecInfo {
+    #[serde(alias = "Running")]
+    running: bool,
+    #[serde(alias = "ExitCode")]
+    exit_code: Option<i32>,
+}
+
+fn start_exec(
+    endpoint: &Endpoint,
+    prefix: &str,
+    container: &Container,
+    exec: &Exec,
+) -> ContainerResult<(String, Box<dyn Read + Send>)> {
+    let env: Vec<String> = exec
+        .env_vars
+        .iter()
+        .map(|env_var| format!("{}={}", env_var.key, env_var.value))
+        .collect();
+
+    let mut body = json!({
+        "AttachStdout": true,
+        "AttachStderr": true,
+        "Cmd": exec.command,
+        "Env": env,
+    });
+
+    if let Some(workdir) = &exec.workdir {
+        body["WorkingDir"] = json!(workdir);
+    }
+
+    if let Some(user) = &exec.user {
+        body["User"] = json!(user);
+    }
+
+    let created: ExecCreated = endpoint
+        .request(
+            "POST",
+            &format!("{prefix}/containers/{}/exec", encode(&container.name)),
+            Some(&body),
+        )?
+        .error_for_status()?
+        .json()?;
+
+    // the output only ends once the command does, which may take any time
+    let start = serde_json::to_vec(&json!({ "Detach": false, "Tty": false }))?;
+    let (response, _) = endpoint.stream(
+        "POST",
+        &format!("{prefix}/exec/{}/start", created.id),
+        Some(("application/json", &start)),
+    )?;
+    let stream = response.error_for_status()?.into_reader();
+
+    Ok((created.id, stream))
+}
+
+///
+/// How long an exec may still be reported as running once its output ended.
+///
+const EXEC_EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn exec_exit_code(endpoint: &Endpoint, prefix: &str, id: &str) -> ContainerResult<Option<i32>> {
+    let deadline = Instant::now() + EXEC_EXIT_TIMEOUT;
+
+    loop {
+        let info: ExecInfo = endpoint
+            .request("GET", &format!("{prefix}/exec/{id}/json"), None)?
+            .error_for_status()?
+            .json()?;
+
+        // the output stream may end slightly before the exec is reported as finished
+        if !info.running {
+            return Ok(info.exit_code);
+        }
+
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("Exec {id} is still running {EXEC_EXIT_TIMEOUT:?} after its output ended"),
+            )
+            .into());
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+///
+/// Executes a command through the exec endpoints, which are the same for the docker and the libpod api.
+///
+pub(crate) fn exec_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    container: &Container,
+    exec: &Exec,
+) -> ContainerResult<ExecOutput> {
+    let (id, stream) = start_exec(endpoint, prefix, container, exec)?;
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+
+    demultiplex(stream, &mut stdout, &mut stderr)?;
+
+    Ok(ExecOutput {
+        stdout: String::from_utf8_lossy(&stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr).into_owned(),
+        exit_code: exec_exit_code(endpoint, prefix, &id)?,
+    })
+}
+
+pub(crate) fn exec_stream_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    container: &Container,
+    exec: &Exec,
+) -> ContainerResult<ExecStream> {
+    let (id, stream) = start_exec(endpoint, prefix, container, exec)?;
+    let (reader, writer) = os_pipe::pipe()?;
+    let stderr = writer.try_clone()?;
+
+    let copy = thread::spawn(move || demultiplex(stream, writer, stderr));
+
+    let endpoint = endpoint.clone();
+    let prefix = prefix.to_string();
+
+    Ok(ExecStream::new(reader, move || {
+        match copy.join() {
+            Ok(result) => result?,
+            Err(_) => debug!("Exec output thread panicked"),
+        }
+
+        exec_exit_code(&endpoint, &prefix, &id)
+    }))
+}
+
+///
+/// Subscribes to container events, the libpod api streams them in the docker format as well.
+///
+pub(crate) fn events_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    filter: &EventFilter,
+) -> ContainerResult<Events> {
+    let mut filters = json!({ "type": ["container"] });
+
+    if !filter.containers.is_empty() {
+        filters["container"] = json!(filter.containers);
+    }
+
+    if !filter.labels.is_empty() {
+        let labels: Vec<String> = filter
+            .labels
+            .iter()
+            .map(|label| format!("{}={}", label.key, label.value))
+            .collect();
+
+        filters["label"] = json!(labels);
+    }
+
+    let mut path = format!("{prefix}/events?filters={}", encode(&filters.to_string()));
+
+    if let Some(since) = filter.since_timestamp() {
+        path.push_str(&format!("&since={since}"));
+    }
+
+    let (response, connection) = endpoint.stream("GET", &path, None)?;
+
+    Ok(Events::new(
+        response.error_for_status()?.into_reader(),
+        connection,
+    ))
+}
+
+#[derive(Deserialize)]
+struct ContainerSummary {
+    #[serde(alias = "Id")]
+    id: String,
+    #[serde(alias = "Names", default)]
+    names: Vec<String>,
+    #[serde(alias = "Labels", default)]
+    labels: Option<HashMap<String, String>>,
+}
+
+///
+/// Removes the containers and networks of contain-rs processes that are gone, see [Client::prune_orphans].
+///
+/// Only the path listing networks differs between the docker and the libpod api.
+///
+pub(crate) fn prune_orphans_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    networks_path: &str,
+) -> ContainerResult<Pruned> {
+    let mut pruned = Pruned::default();
+    let filters = encode(&json!({ "label": [PID_LABEL] }).to_string());
+
+    let containers: Vec<ContainerSummary> = endpoint
+        .request(
+            "GET",
+            &format!("{prefix}/containers/json?all=true&filters={filters}"),
+            None,
+        )?
+        .error_for_status()?
+        .json()?;
+
+    // containers are removed first, as networks can not be removed while in use
+    for container in containers
+        .into_iter()
+        .filter(|container| container.labels.as_ref().is_some_and(is_orphan))
+    {
+        let name = match container.names.first() {
+            Some(name) => name.trim_start_matches('/').to_string(),
+            None => container.id.clone(),
+        };
+
+        info!(container = name, "Removing orphaned container");
+
+        let response = endpoint.request(
+            "DELETE",
+            &format!("{prefix}/containers/{}?force=true", container.id),
+            None,
+        )?;
+
+        // another process might have removed the container in the meantime
+        if response.status != 404 {
+            response.error_for_status()?;
+            pruned.containers.push(name);
+        }
+    }
+
+    let networks: Vec<NetworkInfo> = endpoint
+        .request(
+            "GET",
+            &format!("{prefix}{networks_path}?filters={filters}"),
+            None,
+        )?
+        .error_for_status()?
+        .json()?;
+
+    for network in networks.into_iter().filter(|n| is_orphan(&n.labels)) {
+        info!(network = network.name, "Removing orphaned network");
+
+        let response = endpoint.request(
+            "DELETE",
+            &format!("{prefix}/networks/{}", encode(&network.name)),
+            None,
+        )?;
+
+        if response.status != 404 {
+            response.error_for_status()?;
+            pruned.networks.push(network.name);
+        }
+    }
+
+    Ok(pruned)
+}
+
+///
+/// Uploads a tar archive that gets extracted at `path` inside the container.
+///
+pub(crate) fn put_archive(
+    endpoint: &Endpoint,
+    prefix: &str,
+    container: &Container,
+    path: &str,
+    archive: &[u8],
+) -> ContainerResult<()> {
+    endpoint
+        .send(
+            "PUT",
+            &format!(
+                "{prefix}/containers/{}/archive?path={}",
+                encode(&container.name),
+                encode(path)
+            ),
+            Some(("application/x-tar", archive)),
+        )?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+pub(crate) fn copy_to_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    container: &Container,
+    host_path: &Path,
+    container_path: &str,
+) -> ContainerResult<()> {
+    // like `docker cp`, an existing directory receives the host path under its own name
+    if is_container_dir(endpoint, prefix, container, container_path)? {
+        let name = host_path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or(Cow::Borrowed("."));
+
+        return put_archive(
+            endpoint,
+            prefix,
+            container,
+            container_path,
+            &archive_path(host_path, &name)?,
+        );
+    }
+
+    let trimmed = container_path.trim_end_matches('/');
+
+    let (parent, name) = match trimmed.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((parent, name)) => (parent, name),
+        None => (".", trimmed),
+    };
+
+    put_archive(
+        endpoint,
+        prefix,
+        container,
+        parent,
+        &archive_path(host_path, name)?,
+    )
+}
+
+///
+/// The part of the path stat the runtimes send along with archives.
+///
+#[derive(Deserialize)]
+struct PathStat {
+    mode: u32,
+}
+
+///
+/// Whether `path` is an existing directory in the container, taken from the path stat header of the archive endpoint.
+///
+fn is_container_dir(
+    endpoint: &Endpoint,
+    prefix: &str,
+    container: &Container,
+    path: &str,
+) -> ContainerResult<bool> {
+    // the directory bit of a go file mode
+    const MODE_DIR: u32 = 1 << 31;
+
+    let response = endpoint.request(
+        "HEAD",
+        &format!(
+            "{prefix}/containers/{}/archive?path={}",
+            encode(&container.name),
+            encode(path)
+        ),
+        None,
+    )?;
+
+    match response.status {
+        404 => return Ok(false),
+        status if !response.is_success() => {
+            // a response to HEAD has no body to take the message from
+            return Err(ContainersError::ApiError {
+                status,
+                message: format!("Failed to stat {path} in {}", container.name),
+            });
+        }
+        _ => {}
+    }
+
+    let stat = response
+        .header("x-docker-container-path-stat")
+        .and_then(|stat| BASE64_STANDARD.decode(stat).ok())
+        .ok_or_else(|| ContainersError::ApiError {
+            status: response.status,
+            message: format!("Missing path stat of {path} in {}", container.name),
+        })?;
+
+    Ok(serde_json::from_slice::<PathStat>(&stat)?.mode & MODE_DIR != 0)
+}
+
+pub(crate) fn copy_from_api(
+    endpoint: &Endpoint,
+    prefix: &str,
+    container: &Container,
+    container_path: &str,
+    host_path: &Path,
+) -> ContainerResult<()> {
+    let archive = endpoint
+        .request(
+            "GET",
+            &format!(
+                "{prefix}/containers/{}/archive?path={}",
+                encode(&container.name),
+                encode(container_path)
+            ),
+            None,
+        )?
+        .error_for_status()?
+        .into_reader();
+
+    // like `docker cp`, an existing directory receives the container path under its own name
+    let host_path = match Path::new(container_path).file_name() {
+        Some(name) if host_path.is_dir() => host_path.join(name),
+        _ => host_path.to_path_buf(),
+    };
+
+    extract_to(archive, &host_path)
+}
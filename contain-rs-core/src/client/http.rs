@@ -0,0 +1,531 @@
+//!
+//! A minimal blocking HTTP/1.1 client used to talk to container engine APIs and to probe containers while waiting for them.
+//!
+//! Engine APIs are usually served on a unix socket, which general purpose http clients do not support without
+//! pulling in an async runtime. Every request opens a fresh connection and asks the server to close it afterwards.
+//!
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    str::FromStr,
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use tracing::*;
+
+use crate::error::{ContainerResult, ContainersError};
+
+use super::shared::daemon_error;
+
+///
+/// How long to wait for an engine to accept a connection, take a request or send the next part of a response.
+///
+/// Requests that are expected to take longer, like stopping a container, wait for that on top.
+///
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(60);
+
+pub(crate) trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+///
+/// The address an engine API is served on.
+///
+/// ```
+/// use contain_rs_core::client::http::Endpoint;
+/// use std::str::FromStr;
+///
+/// assert!(Endpoint::from_str("unix:///var/run/docker.sock").is_ok());
+/// assert!(Endpoint::from_str("tcp://localhost:2375").is_ok());
+/// assert!(Endpoint::from_str("ssh://localhost").is_err());
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    #[cfg(unix)]
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl FromStr for Endpoint {
+    type Err = ContainersError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix://") {
+            return Ok(Endpoint::Unix(PathBuf::from(path)));
+        }
+
+        if let Some(address) = s
+            .strip_prefix("tcp://")
+            .or_else(|| s.strip_prefix("http://"))
+        {
+            return Ok(Endpoint::Tcp(address.trim_end_matches('/').to_string()));
+        }
+
+        Err(ContainersError::InvalidEndpoint {
+            endpoint: s.to_string(),
+        })
+    }
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix://{}", path.display()),
+            Endpoint::Tcp(address) => write!(f, "tcp://{address}"),
+        }
+    }
+}
+
+impl Endpoint {
+    fn open(&self) -> ContainerResult<Connection> {
+        let connection = match self {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => UnixStream::connect(path).map(Connection::Unix),
+            Endpoint::Tcp(address) => connect_tcp(address).map(Connection::Tcp),
+        };
+
+        let connection = connection.map_err(|e| ContainersError::DaemonUnreachable {
+            message: format!("{self}: {e}"),
+        })?;
+
+        connection.set_read_timeout(Some(TIMEOUT))?;
+        connection.set_write_timeout(Some(TIMEOUT))?;
+
+        Ok(connection)
+    }
+
+    ///
+    /// The host the engine runs on, which is where it publishes the ports of containers.
+    ///
+    /// ```
+    /// use contain_rs_core::client::http::Endpoint;
+    /// use std::str::FromStr;
+    ///
+    /// assert_eq!(Endpoint::from_str("tcp://10.0.0.2:2375").unwrap().host(), "10.0.0.2");
+    /// assert_eq!(Endpoint::from_str("tcp://[::1]:2375").unwrap().host(), "::1");
+    /// assert_eq!(Endpoint::from_str("unix:///var/run/docker.sock").unwrap().host(), "localhost");
+    /// ```
+    ///
+    pub fn host(&self) -> &str {
+        match self {
+            #[cfg(unix)]
+            Endpoint::Unix(_) => "localhost",
+            Endpoint::Tcp(address) => address
+                .rsplit_once(':')
+                .filter(|(host, _)| !host.is_empty())
+                .map_or(address.as_str(), |(host, _)| host)
+                .trim_start_matches('[')
+                .trim_end_matches(']'),
+        }
+    }
+
+    fn host_header(&self) -> &str {
+        match self {
+            #[cfg(unix)]
+            Endpoint::Unix(_) => "localhost",
+            Endpoint::Tcp(address) => address,
+        }
+    }
+
+    ///
+    /// Send a request and return the response with a body that can be streamed.
+    ///
+    pub(crate) fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> ContainerResult<Response> {
+        match body {
+            Some(body) => self.send(
+                method,
+                path,
+                Some(("application/json", &serde_json::to_vec(body)?)),
+            ),
+            None => self.send(method, path, None),
+        }
+    }
+
+    ///
+    /// Send a request that takes up to `timeout` longer than usual to be answered, like stopping a container.
+    ///
+    pub(crate) fn request_within(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+        timeout: Duration,
+    ) -> ContainerResult<Response> {
+        let connection = self.open()?;
+
+        connection.set_read_timeout(Some(TIMEOUT + timeout))?;
+
+        let body = body.map(serde_json::to_vec).transpose()?;
+        let body = body.as_deref().map(|body| ("application/json", body));
+
+        exchange(
+            connection.into_stream(),
+            self.host_header(),
+            method,
+            path,
+            body,
+        )
+    }
+
+    ///
+    /// Send a request whose response is read for as long as the server keeps sending,
+    /// together with the connection to close it from another thread.
+    ///
+    /// Only the response head has to arrive in time, the body may pause for as long as the server needs.
+    ///
+    pub(crate) fn stream(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<(&str, &[u8])>,
+    ) -> ContainerResult<(Response, Connection)> {
+        let connection = self.open()?;
+        let response = exchange(
+            connection.try_clone()?.into_stream(),
+            self.host_header(),
+            method,
+            path,
+            body,
+        )?;
+
+        connection.set_read_timeout(None)?;
+
+        Ok((response, connection))
+    }
+
+    ///
+    /// Send a request with a raw body of the given content type.
+    ///
+    pub(crate) fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<(&str, &[u8])>,
+    ) -> ContainerResult<Response> {
+        exchange(
+            self.open()?.into_stream(),
+            self.host_header(),
+            method,
+            path,
+            body,
+        )
+    }
+}
+
+fn connect_tcp(address: &str) -> io::Result<TcpStream> {
+    let mut error = io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{address} does not resolve to any address"),
+    );
+
+    for address in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e,
+        }
+    }
+
+    Err(error)
+}
+
+///
+/// A connection to an engine.
+///
+pub(crate) enum Connection {
+    #[cfg(unix)]
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Connection {
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.try_clone().map(Connection::Unix),
+            Connection::Tcp(stream) => stream.try_clone().map(Connection::Tcp),
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_read_timeout(timeout),
+            Connection::Tcp(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_write_timeout(timeout),
+            Connection::Tcp(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+
+    fn into_stream(self) -> Box<dyn Stream> {
+        match self {
+            #[cfg(unix)]
+            Connection::Unix(stream) => Box::new(stream),
+            Connection::Tcp(stream) => Box::new(stream),
+        }
+    }
+
+    ///
+    /// Closes the connection, which ends reading from it on every handle.
+    ///
+    pub(crate) fn shutdown(&self) {
+        let result = match self {
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.shutdown(Shutdown::Both),
+            Connection::Tcp(stream) => stream.shutdown(Shutdown::Both),
+        };
+
+        if let Err(e) = result {
+            debug!(%e, "Failed to close connection");
+        }
+    }
+}
+
+///
+/// Writes a request to an already connected stream and reads the response.
+///
+pub(crate) fn exchange(
+    mut stream: Box<dyn Stream>,
+    host: &str,
+    method: &str,
+    path: &str,
+    body: Option<(&str, &[u8])>,
+) -> ContainerResult<Response> {
+    let mut head = format!("{method} {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n");
+
+    match body {
+        Some((content_type, body)) => head.push_str(&format!(
+            "Content-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+            body.len()
+        )),
+        None => head.push_str("Content-Length: 0\r\n\r\n"),
+    }
+
+    stream.write_all(head.as_bytes())?;
+
+    if let Some((_, body)) = body {
+        stream.write_all(body)?;
+    }
+
+    stream.flush()?;
+
+    Response::read(BufReader::new(stream))
+}
+
+pub(crate) struct Response {
+    pub status: u16,
+    headers: HashMap<String, String>,
+    body: Box<dyn Read + Send>,
+}
+
+///
+/// The error body of the docker api has a `message`, libpod adds the `cause` of the error.
+///
+#[derive(Deserialize)]
+struct ErrorMessage {
+    message: Option<String>,
+    cause: Option<String>,
+}
+
+impl Response {
+    fn read(mut reader: BufReader<Box<dyn Stream>>) -> ContainerResult<Self> {
+        let status_line = read_line(&mut reader)?;
+        let status = status_line
+            .split(' ')
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| invalid_data(format!("Invalid status line: {status_line}")))?;
+
+        let mut headers = HashMap::new();
+
+        loop {
+            let line = read_line(&mut reader)?;
+
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let content_length = headers
+            .get("content-length")
+            .and_then(|value| value.parse::<u64>().ok());
+        let chunked = headers
+            .get("transfer-encoding")
+            .is_some_and(|value| value.eq_ignore_ascii_case("chunked"));
+
+        let body: Box<dyn Read + Send> = if chunked {
+            Box::new(ChunkedReader {
+                inner: reader,
+                remaining: 0,
+                done: false,
+            })
+        } else if let Some(length) = content_length {
+            Box::new(reader.take(length))
+        } else {
+            Box::new(reader)
+        };
+
+        Ok(Self {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    ///
+    /// The value of a response header, `name` has to be lower case.
+    ///
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn text(mut self) -> ContainerResult<String> {
+        let mut body = String::new();
+        self.body.read_to_string(&mut body)?;
+        Ok(body)
+    }
+
+    pub fn json<T: DeserializeOwned>(self) -> ContainerResult<T> {
+        Ok(serde_json::from_str(&self.text()?)?)
+    }
+
+    pub fn into_reader(self) -> Box<dyn Read + Send> {
+        self.body
+    }
+
+    ///
+    /// Returns the response when it was successful, or converts it into an error.
+    ///
+    /// Well known daemon errors are reported as their own [ContainersError] variant, like the clis report them,
+    /// anything else ends up in a [ContainersError::ApiError].
+    ///
+    pub fn error_for_status(self) -> ContainerResult<Self> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(self.into_error())
+        }
+    }
+
+    pub fn into_error(self) -> ContainersError {
+        let status = self.status;
+
+        let message = match self.text() {
+            Ok(text) => serde_json::from_str::<ErrorMessage>(&text)
+                .ok()
+                .and_then(|error| error.message.or(error.cause))
+                .unwrap_or(text),
+            Err(e) => e.to_string(),
+        };
+
+        let message = message.trim().to_string();
+
+        daemon_error(&message).unwrap_or(ContainersError::ApiError { status, message })
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Connection closed while reading response",
+        ));
+    }
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct ChunkedReader<R: BufRead> {
+    inner: R,
+    remaining: u64,
+    done: bool,
+}
+
+impl<R: BufRead> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let line = read_line(&mut self.inner)?;
+            let size = line.split(';').next().unwrap_or_default().trim();
+
+            self.remaining = u64::from_str_radix(size, 16)
+                .map_err(|_| invalid_data(format!("Invalid chunk size: {line}")))?;
+
+            if self.remaining == 0 {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+
+        let max = buf.len().min(self.remaining as usize);
+        let read = self.inner.read(&mut buf[..max])?;
+
+        if read == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Connection closed in the middle of a chunk",
+            ));
+        }
+
+        self.remaining -= read as u64;
+
+        if self.remaining == 0 {
+            // every chunk is terminated by CRLF
+            read_line(&mut self.inner)?;
+        }
+
+        Ok(read)
+    }
+}
+
+///
+/// Percent encode a value to be used in a query string or path segment.
+///
+pub(crate) fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
@@ -5,21 +5,38 @@
 //!
 
 use std::{
-    io::{BufRead, BufReader},
-    process::Command,
+    io::{self, BufRead, BufReader, Read, Write},
+    mem,
+    path::Path,
+    process::{Child, Command},
+    sync::mpsc,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use os_pipe::PipeReader;
+use tracing::*;
 
 use crate::{
-    container::{Container, IntoContainer},
-    error::ContainerResult,
-    rt::DetailedContainerInfo,
+    container::{Container, Exec, Image, ImageBuild, IntoContainer, Label, Network, Port},
+    error::{ContainerResult, ContainersError},
+    rt::{DetailedContainerInfo, Event, ExecOutput},
 };
 
+use self::{http::Connection, shared::command_error};
+
+mod archive;
+#[cfg(feature = "async")]
+pub mod async_client;
 pub mod docker;
+pub mod docker_api;
+pub mod http;
 pub mod podman;
+#[cfg(unix)]
+pub mod podman_api;
+pub mod reaper;
 pub mod shared;
+mod wait;
 
 ///
 /// The client Trait represents a way to access a client.
@@ -31,49 +48,492 @@ pub mod shared;
 pub trait Client: Clone {
     type ClientType: Client;
 
+    ///
+    /// The cli command that talks to the same runtime as this client, for anything the client does not cover.
+    ///
     fn command(&self) -> Command;
     fn create<C: IntoContainer>(&self, container: C) -> ContainerHandle<Self::ClientType>;
     fn run(&self, container: &Container) -> ContainerResult<()>;
+    ///
+    /// Stops the container, killing it once its [stop timeout](Container::stop_timeout) passed.
+    ///
     fn stop(&self, container: &Container) -> ContainerResult<()>;
+    ///
+    /// Freezes all processes of the container until it is [unpaused](Client::unpause).
+    ///
+    fn pause(&self, container: &Container) -> ContainerResult<()>;
+    fn unpause(&self, container: &Container) -> ContainerResult<()>;
+    ///
+    /// Stops and starts the container again, the [stop timeout](Container::stop_timeout) applies as well.
+    ///
+    fn restart(&self, container: &Container) -> ContainerResult<()>;
+    ///
+    /// Sends a signal to the main process of the container, e.g. `SIGKILL` or `SIGHUP`.
+    ///
+    fn kill(&self, container: &Container, signal: &str) -> ContainerResult<()>;
     fn rm(&self, container: &Container) -> ContainerResult<()>;
+    ///
+    /// Follows the output of the container from its start until it stops, none if it does not exist.
+    ///
     fn log(&self, container: &Container) -> ContainerResult<Option<Log>>;
     fn inspect(&self, container: &Container) -> ContainerResult<Option<DetailedContainerInfo>>;
     fn exists(&self, container: &Container) -> ContainerResult<bool>;
     fn runs(&self, container: &Container) -> ContainerResult<bool>;
     fn wait(&self, container: &Container) -> ContainerResult<()>;
+    fn pull(&self, image: &Image) -> ContainerResult<()>;
+    fn image_exists(&self, image: &Image) -> ContainerResult<bool>;
+    fn build_image<F: FnMut(&str)>(&self, build: &ImageBuild, output: F) -> ContainerResult<Image>;
+    fn copy_to(
+        &self,
+        container: &Container,
+        host_path: &Path,
+        container_path: &str,
+    ) -> ContainerResult<()>;
+    fn copy_from(
+        &self,
+        container: &Container,
+        container_path: &str,
+        host_path: &Path,
+    ) -> ContainerResult<()>;
+    fn exec(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecOutput>;
+    fn exec_stream(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecStream>;
+    ///
+    /// Subscribes to the events of the containers matching the filter.
+    ///
+    fn events(&self, filter: &EventFilter) -> ContainerResult<Events>;
+    fn network(&self, network: Network) -> NetworkHandle<Self::ClientType>;
+    fn create_network(&self, network: &Network) -> ContainerResult<()>;
+    fn rm_network(&self, network: &Network) -> ContainerResult<()>;
+    fn network_exists(&self, network: &Network) -> ContainerResult<bool>;
+    fn connect_network(
+        &self,
+        network: &Network,
+        container: &Container,
+        aliases: &[String],
+    ) -> ContainerResult<()>;
+    ///
+    /// Removes the containers and networks that were created by contain-rs processes which are gone,
+    /// e.g. because they got killed before they could clean up.
+    ///
+    /// Whether a process is gone is checked on this machine, see [crate::session].
+    ///
+    fn prune_orphans(&self) -> ContainerResult<Pruned>;
+    ///
+    /// Opts into the [reaper](crate::client::reaper), which removes everything of the session once the process is gone,
+    /// even when it got killed. The reaper is started once per runtime and stays connected until the process exits.
+    ///
+    fn start_reaper(&self) -> ContainerResult<()>;
+    ///
+    /// The host the runtime publishes the ports of containers on, e.g. the host of a remote `DOCKER_HOST`.
+    ///
+    fn host(&self) -> String;
 }
 
 ///
 /// A handle is a way to interact with a container.
 ///
 /// When you create a container using a [Client] it will return one of these.
-/// The handle automatically stops and removes the container, when it goes out of scope.
+/// The handle automatically cleans up the container according to its [CleanupPolicy], when it goes out of scope.
 ///
 pub trait Handle {
     fn run(&self) -> ContainerResult<()>;
     fn wait(&self) -> ContainerResult<()>;
     fn run_and_wait(&self) -> ContainerResult<()>;
     fn stop(&self) -> ContainerResult<()>;
+    fn pause(&self) -> ContainerResult<()>;
+    fn unpause(&self) -> ContainerResult<()>;
+    fn restart(&self) -> ContainerResult<()>;
+    fn kill(&self, signal: &str) -> ContainerResult<()>;
     fn rm(&self) -> ContainerResult<()>;
     fn log(&self) -> ContainerResult<Option<Log>>;
     fn container(&self) -> &Container;
     fn is_running(&self) -> ContainerResult<bool>;
     fn exists(&self) -> ContainerResult<bool>;
+    fn host_port(&self, port: Port) -> ContainerResult<Option<u16>>;
+    fn copy_to(&self, host_path: &Path, container_path: &str) -> ContainerResult<()>;
+    fn copy_from(&self, container_path: &str, host_path: &Path) -> ContainerResult<()>;
+    fn exec(&self, exec: &Exec) -> ContainerResult<ExecOutput>;
+    fn exec_stream(&self, exec: &Exec) -> ContainerResult<ExecStream>;
+    fn events(&self) -> ContainerResult<Events>;
+    fn cleanup(&self) -> ContainerResult<()>;
+}
+
+///
+/// Decides what happens to the container of a [ContainerHandle] once it is cleaned up.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CleanupPolicy {
+    ///
+    /// Stop and remove the container.
+    ///
+    #[default]
+    Remove,
+    ///
+    /// Only stop the container, so it can still be inspected afterwards.
+    ///
+    Stop,
+    ///
+    /// Remove the container, unless the current thread is panicking (e.g. a failing test).
+    /// The container is kept running for debugging then.
+    ///
+    KeepOnFailure,
 }
 
+///
+/// The output of a container, from the moment it started.
+///
+/// Every line is available on the merged [reader](Log::reader) and on the reader of its own stream.
+/// Lines of a reader that is not read are kept in memory, until the log is dropped.
+///
+/// A cli reading the log is killed and the connection to an engine is closed once this is dropped.
+///
 pub struct Log {
+    ///
+    /// Both streams of the container, merged line by line.
+    ///
     pub reader: PipeReader,
+    pub stdout: PipeReader,
+    pub stderr: PipeReader,
+    process: Option<Child>,
+    connection: Option<Connection>,
 }
 
 impl Log {
-    fn stream(&mut self) -> impl BufRead {
-        BufReader::new(self.reader.try_clone().unwrap())
+    pub(crate) fn new(
+        stdout: PipeReader,
+        stderr: PipeReader,
+        connection: Connection,
+    ) -> io::Result<Self> {
+        let (reader, stdout, stderr) = split_log(stdout, stderr)?;
+
+        Ok(Self {
+            reader,
+            stdout,
+            stderr,
+            process: None,
+            connection: Some(connection),
+        })
+    }
+
+    pub(crate) fn from_process(
+        stdout: PipeReader,
+        stderr: PipeReader,
+        child: Child,
+    ) -> io::Result<Self> {
+        let (reader, stdout, stderr) = split_log(stdout, stderr)?;
+
+        Ok(Self {
+            reader,
+            stdout,
+            stderr,
+            process: Some(child),
+            connection: None,
+        })
+    }
+}
+
+///
+/// Copies every line of both streams to a pipe of its stream and to the merged pipe.
+///
+fn split_log(
+    stdout: PipeReader,
+    stderr: PipeReader,
+) -> io::Result<(PipeReader, PipeReader, PipeReader)> {
+    let (merged, merged_lines) = buffered_pipe()?;
+    let (stdout_reader, stdout_lines) = buffered_pipe()?;
+    let (stderr_reader, stderr_lines) = buffered_pipe()?;
+
+    for (source, lines) in [(stdout, stdout_lines), (stderr, stderr_lines)] {
+        let merged_lines = merged_lines.clone();
+
+        thread::spawn(move || {
+            let mut source = BufReader::new(source);
+
+            loop {
+                let mut line = Vec::new();
+
+                match source.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {
+                        let merged = merged_lines.send(line.clone());
+
+                        if lines.send(line).is_err() && merged.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    Ok((merged, stdout_reader, stderr_reader))
+}
+
+///
+/// A pipe written by a thread of its own, so a reader that is not read does not hold up the others.
+///
+fn buffered_pipe() -> io::Result<(PipeReader, mpsc::Sender<Vec<u8>>)> {
+    let (reader, mut writer) = os_pipe::pipe()?;
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+
+    thread::spawn(move || {
+        for chunk in receiver {
+            if writer.write_all(&chunk).is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok((reader, sender))
+}
+
+impl Drop for Log {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.process.take() {
+            if let Err(e) = child.kill().and_then(|_| child.wait()) {
+                debug!(%e, "Failed to stop reading the log");
+            }
+        }
+
+        if let Some(connection) = &self.connection {
+            connection.shutdown();
+        }
+    }
+}
+
+///
+/// The output of a command executed inside a container, while it is still running.
+///
+/// Stdout and stderr are merged into a single stream.
+/// Consume the stream before calling [ExecStream::wait], otherwise the command might block on a full pipe.
+///
+pub struct ExecStream {
+    pub reader: PipeReader,
+    exit_code: Box<dyn FnOnce() -> ContainerResult<Option<i32>> + Send>,
+}
+
+impl ExecStream {
+    pub(crate) fn new(
+        reader: PipeReader,
+        exit_code: impl FnOnce() -> ContainerResult<Option<i32>> + Send + 'static,
+    ) -> Self {
+        Self {
+            reader,
+            exit_code: Box::new(exit_code),
+        }
+    }
+
+    pub fn stream(&mut self) -> impl BufRead + '_ {
+        BufReader::new(&mut self.reader)
+    }
+
+    ///
+    /// Waits for the command to finish and returns its exit code.
+    ///
+    pub fn wait(self) -> ContainerResult<Option<i32>> {
+        (self.exit_code)()
+    }
+}
+
+///
+/// The resources removed by [Client::prune_orphans].
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pruned {
+    pub containers: Vec<String>,
+    pub networks: Vec<String>,
+}
+
+///
+/// Selects the events to subscribe to with [Client::events].
+///
+/// Events of any of the given containers are reported, which need to have all of the given labels.
+///
+/// ```
+/// use contain_rs_core::client::EventFilter;
+///
+/// let filter = EventFilter::new()
+///     .container("postgres")
+///     .label("com.example.stack", "backend");
+///
+/// assert_eq!(filter.containers, vec!["postgres".to_string()]);
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    pub containers: Vec<String>,
+    pub labels: Vec<Label>,
+    ///
+    /// Also report past events from this point in time on, only new events are reported otherwise.
+    ///
+    pub since: Option<SystemTime>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///
+    /// Only report events of a container, by name or id.
+    ///
+    pub fn container(mut self, container: &str) -> Self {
+        self.containers.push(container.into());
+        self
+    }
+
+    pub fn label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.labels.push((key, value).into());
+        self
+    }
+
+    pub fn since(mut self, since: SystemTime) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    ///
+    /// The start of the filter as unix timestamp, which both runtimes understand.
+    ///
+    pub(crate) fn since_timestamp(&self) -> Option<u64> {
+        self.since.map(|since| {
+            since
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        })
+    }
+}
+
+///
+/// A subscription to container events, see [Client::events].
+///
+/// Iterating blocks until the next event arrives. The subscription ends when this is dropped.
+///
+pub struct Events {
+    reader: Box<dyn BufRead + Send>,
+    process: Option<(Command, Child)>,
+    connection: Option<Connection>,
+}
+
+impl Events {
+    ///
+    /// Reads the events an engine streams over the connection, which is closed once the subscription ends.
+    ///
+    pub(crate) fn new(stream: impl Read + Send + 'static, connection: Connection) -> Self {
+        Self {
+            reader: Box::new(BufReader::new(stream)),
+            process: None,
+            connection: Some(connection),
+        }
+    }
+
+    ///
+    /// Reads the events a cli prints to stdout, the process is killed once the subscription ends.
+    ///
+    pub(crate) fn from_process(command: Command, mut child: Child) -> Self {
+        let reader: Box<dyn BufRead + Send> = match child.stdout.take() {
+            Some(stdout) => Box::new(BufReader::new(stdout)),
+            None => Box::new(std::io::empty()),
+        };
+
+        Self {
+            reader,
+            process: Some((command, child)),
+            connection: None,
+        }
+    }
+
+    ///
+    /// Takes the events to read them on another thread.
+    ///
+    /// The subscription stays with this, dropping it ends the taken events as well.
+    ///
+    pub(crate) fn take_stream(&mut self) -> Events {
+        Events {
+            reader: mem::replace(&mut self.reader, Box::new(io::empty())),
+            process: None,
+            connection: None,
+        }
+    }
+
+    ///
+    /// Reports a failed cli once all of its output has been read.
+    ///
+    fn finish(&mut self) -> ContainerResult<()> {
+        if let Some((command, child)) = self.process.take() {
+            let output = child.wait_with_output()?;
+
+            if !output.status.success() {
+                return Err(command_error(&command, output));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Iterator for Events {
+    type Item = ContainerResult<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return self.finish().err().map(Err),
+                Ok(_) if line.trim().is_empty() => continue,
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+
+            match Event::from_json(&line) {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+impl Drop for Events {
+    fn drop(&mut self) {
+        if let Some((_, mut child)) = self.process.take() {
+            if let Err(e) = child.kill().and_then(|_| child.wait()) {
+                debug!(%e, "Failed to stop listening for events");
+            }
+        }
+
+        if let Some(connection) = &self.connection {
+            connection.shutdown();
+        }
     }
 }
 
 pub struct ContainerHandle<T: Client> {
     client: T,
     container: Container,
+    cleanup_policy: CleanupPolicy,
+}
+
+impl<T: Client> ContainerHandle<T> {
+    pub(crate) fn new(client: T, container: Container) -> Self {
+        Self {
+            client,
+            container,
+            cleanup_policy: CleanupPolicy::default(),
+        }
+    }
+
+    ///
+    /// Set what happens to the container on [Handle::cleanup] and when the handle is dropped.
+    ///
+    pub fn cleanup_policy(mut self, cleanup_policy: CleanupPolicy) -> Self {
+        self.cleanup_policy = cleanup_policy;
+        self
+    }
 }
 
 impl<T: Client> Handle for ContainerHandle<T> {
@@ -108,6 +568,31 @@ impl<T: Client> Handle for ContainerHandle<T> {
         Ok(())
     }
 
+    ///
+    /// Freezes all processes of the container, e.g. to simulate an unresponsive service.
+    ///
+    fn pause(&self) -> ContainerResult<()> {
+        self.client.pause(&self.container)
+    }
+
+    fn unpause(&self) -> ContainerResult<()> {
+        self.client.unpause(&self.container)
+    }
+
+    ///
+    /// Restarts the container, which does not wait for it to be ready again, see [Handle::wait].
+    ///
+    fn restart(&self) -> ContainerResult<()> {
+        self.client.restart(&self.container)
+    }
+
+    ///
+    /// Sends a signal to the main process of the container, e.g. `SIGKILL` to simulate a crash.
+    ///
+    fn kill(&self, signal: &str) -> ContainerResult<()> {
+        self.client.kill(&self.container, signal)
+    }
+
     fn rm(&self) -> ContainerResult<()> {
         self.stop()?;
 
@@ -119,11 +604,7 @@ impl<T: Client> Handle for ContainerHandle<T> {
     }
 
     fn log(&self) -> ContainerResult<Option<Log>> {
-        if self.is_running()? {
-            Ok(self.client.log(&self.container)?)
-        } else {
-            Ok(None)
-        }
+        self.client.log(&self.container)
     }
 
     fn container(&self) -> &Container {
@@ -137,10 +618,153 @@ impl<T: Client> Handle for ContainerHandle<T> {
     fn exists(&self) -> ContainerResult<bool> {
         self.client.exists(&self.container)
     }
+
+    ///
+    /// Returns the host port a container port has been published on.
+    ///
+    /// This is useful when the host port was picked by the runtime, see [Container::expose_port].
+    ///
+    fn host_port(&self, port: Port) -> ContainerResult<Option<u16>> {
+        match self.client.inspect(&self.container)? {
+            Some(info) => Ok(info.host_port(port)),
+            None => Err(ContainersError::ContainerNotExists {
+                container_name: self.container.name.clone(),
+            }),
+        }
+    }
+
+    ///
+    /// Copy a file or directory from the host into the container, it ends up at `container_path`.
+    ///
+    fn copy_to(&self, host_path: &Path, container_path: &str) -> ContainerResult<()> {
+        self.client
+            .copy_to(&self.container, host_path, container_path)
+    }
+
+    ///
+    /// Copy a file or directory out of the container, it ends up at `host_path`.
+    ///
+    fn copy_from(&self, container_path: &str, host_path: &Path) -> ContainerResult<()> {
+        self.client
+            .copy_from(&self.container, container_path, host_path)
+    }
+
+    ///
+    /// Executes a command inside the running container and waits for it to finish.
+    ///
+    fn exec(&self, exec: &Exec) -> ContainerResult<ExecOutput> {
+        self.client.exec(&self.container, exec)
+    }
+
+    ///
+    /// Executes a command inside the running container, streaming its output.
+    ///
+    fn exec_stream(&self, exec: &Exec) -> ContainerResult<ExecStream> {
+        self.client.exec_stream(&self.container, exec)
+    }
+
+    ///
+    /// Subscribes to the events of the container.
+    ///
+    fn events(&self) -> ContainerResult<Events> {
+        self.client
+            .events(&EventFilter::new().container(&self.container.name))
+    }
+
+    ///
+    /// Stops and/or removes the container as configured by its [CleanupPolicy].
+    ///
+    fn cleanup(&self) -> ContainerResult<()> {
+        match self.cleanup_policy {
+            CleanupPolicy::Remove => self.rm(),
+            CleanupPolicy::Stop => self.stop(),
+            CleanupPolicy::KeepOnFailure if thread::panicking() => {
+                info!(container = %self.container.name, "Keeping container of failed test");
+                Ok(())
+            }
+            CleanupPolicy::KeepOnFailure => self.rm(),
+        }
+    }
 }
 
 impl<T: Client> Drop for ContainerHandle<T> {
     fn drop(&mut self) {
-        self.rm().unwrap();
+        if let Err(e) = self.cleanup() {
+            warn!(%e, container = %self.container.name, "Failed to clean up container");
+        }
+    }
+}
+
+///
+/// A network handle is a way to interact with a [Network].
+///
+/// When you create a network handle using a [Client] the network is not created yet, call [NetworkHandle::create] for that.
+/// The handle automatically removes the network, when it goes out of scope.
+///
+/// ```no_run
+/// use contain_rs_core::{
+///     client::{docker::Docker, Client, Handle},
+///     container::{Container, Image, Network},
+/// };
+/// use std::str::FromStr;
+///
+/// let client = Docker::new();
+/// let network = client.network(Network::default());
+///
+/// network.create().unwrap();
+///
+/// let mut container = Container::from_image(Image::from_str("docker.io/library/postgres").unwrap());
+///
+/// container.network(network.network()).network_alias("db");
+///
+/// let postgres = client.create(container);
+///
+/// postgres.run().unwrap();
+/// ```
+///
+pub struct NetworkHandle<T: Client> {
+    client: T,
+    network: Network,
+}
+
+impl<T: Client> NetworkHandle<T> {
+    pub fn create(&self) -> ContainerResult<()> {
+        if !self.exists()? {
+            self.client.create_network(&self.network)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn rm(&self) -> ContainerResult<()> {
+        if self.exists()? {
+            self.client.rm_network(&self.network)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn exists(&self) -> ContainerResult<bool> {
+        self.client.network_exists(&self.network)
+    }
+
+    ///
+    /// Attach an already created container to this network.
+    ///
+    pub fn connect(&self, container: &Container, aliases: &[String]) -> ContainerResult<()> {
+        self.client
+            .connect_network(&self.network, container, aliases)
+    }
+
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+}
+
+impl<T: Client> Drop for NetworkHandle<T> {
+    fn drop(&mut self) {
+        if let Err(e) = self.rm() {
+            warn!(%e, network = %self.network.name, "Failed to remove network");
+        }
     }
 }
@@ -1,15 +1,26 @@
-use std::process::Command;
+use std::{path::Path, process::Command};
 
-use crate::{container::*, error::ContainerResult, rt::DetailedContainerInfo};
+use crate::{
+    container::*,
+    error::ContainerResult,
+    rt::{DetailedContainerInfo, ExecOutput},
+};
 
 use super::{
+    reaper,
     shared::{
-        build_rm_command, build_run_command, build_stop_command, do_log, inspect,
-        run_and_wait_for_command, wait_for,
+        self, build_copy_from_command, build_copy_to_command, build_image, build_kill_command,
+        build_network_connect_command, build_network_create_command, build_network_rm_command,
+        build_pause_command, build_restart_command, build_rm_command, build_run_command,
+        build_stop_command, build_unpause_command, do_log, ensure_image, ensure_volumes,
+        image_exists, inspect, network_exists, run_and_wait_for_command, run_with_files, wait_for,
     },
-    Client, ContainerHandle, Log,
+    Client, ContainerHandle, EventFilter, Events, ExecStream, Log, NetworkHandle, Pruned,
 };
 
+#[cfg(unix)]
+use super::podman_api::PodmanApi;
+
 ///
 /// The Podman struct is used for acessing the podman cli.
 ///
@@ -64,13 +75,17 @@ impl Client for Podman {
     }
 
     fn create<C: IntoContainer>(&self, container: C) -> ContainerHandle<Podman> {
-        ContainerHandle {
-            client: self.to_owned(),
-            container: container.into_container(),
-        }
+        ContainerHandle::new(self.to_owned(), container.into_container())
     }
 
     fn run(&self, container: &Container) -> ContainerResult<()> {
+        ensure_image(self, container)?;
+        ensure_volumes(|| self.build_command(), container)?;
+
+        if !container.files.is_empty() {
+            return run_with_files(|| self.build_command(), container);
+        }
+
         let mut command = self.build_command();
 
         build_run_command(&mut command, container);
@@ -88,6 +103,42 @@ impl Client for Podman {
         Ok(())
     }
 
+    fn pause(&self, container: &Container) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_pause_command(&mut command, container);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn unpause(&self, container: &Container) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_unpause_command(&mut command, container);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn restart(&self, container: &Container) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_restart_command(&mut command, container);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn kill(&self, container: &Container, signal: &str) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_kill_command(&mut command, container, signal);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
     fn rm(&self, container: &Container) -> ContainerResult<()> {
         let mut command = self.build_command();
 
@@ -98,7 +149,7 @@ impl Client for Podman {
     }
 
     fn log(&self, container: &Container) -> ContainerResult<Option<Log>> {
-        if self.runs(container)? {
+        if self.exists(container)? {
             Ok(Some(do_log(self, container)?))
         } else {
             Ok(None)
@@ -123,4 +174,122 @@ impl Client for Podman {
     fn wait(&self, container: &Container) -> ContainerResult<()> {
         wait_for(self, container)
     }
+
+    fn pull(&self, image: &Image) -> ContainerResult<()> {
+        shared::pull(&mut self.build_command(), image)
+    }
+
+    fn image_exists(&self, image: &Image) -> ContainerResult<bool> {
+        image_exists(&mut self.build_command(), image)
+    }
+
+    fn build_image<F: FnMut(&str)>(&self, build: &ImageBuild, output: F) -> ContainerResult<Image> {
+        build_image(&mut self.build_command(), build, output)
+    }
+
+    fn copy_to(
+        &self,
+        container: &Container,
+        host_path: &Path,
+        container_path: &str,
+    ) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_copy_to_command(&mut command, container, host_path, container_path);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn copy_from(
+        &self,
+        container: &Container,
+        container_path: &str,
+        host_path: &Path,
+    ) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_copy_from_command(&mut command, container, container_path, host_path);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn exec(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecOutput> {
+        shared::exec(&mut self.build_command(), container, exec)
+    }
+
+    fn exec_stream(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecStream> {
+        shared::exec_stream(&mut self.build_command(), container, exec)
+    }
+
+    fn events(&self, filter: &EventFilter) -> ContainerResult<Events> {
+        shared::events(self.build_command(), filter)
+    }
+
+    fn network(&self, network: Network) -> NetworkHandle<Self::ClientType> {
+        NetworkHandle {
+            client: self.clone(),
+            network,
+        }
+    }
+
+    fn create_network(&self, network: &Network) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_network_create_command(&mut command, network);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn rm_network(&self, network: &Network) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_network_rm_command(&mut command, network);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn network_exists(&self, network: &Network) -> ContainerResult<bool> {
+        network_exists(&mut self.build_command(), network)
+    }
+
+    fn connect_network(
+        &self,
+        network: &Network,
+        container: &Container,
+        aliases: &[String],
+    ) -> ContainerResult<()> {
+        let mut command = self.build_command();
+
+        build_network_connect_command(&mut command, network, container, aliases);
+        run_and_wait_for_command(&mut command)?;
+
+        Ok(())
+    }
+
+    fn prune_orphans(&self) -> ContainerResult<Pruned> {
+        shared::prune_orphans(|| self.build_command())
+    }
+
+    fn start_reaper(&self) -> ContainerResult<()> {
+        #[cfg(unix)]
+        let socket = reaper::socket(PodmanApi::new().endpoint());
+        #[cfg(not(unix))]
+        let socket = reaper::PODMAN_SOCKET;
+
+        reaper::ensure_started(self, socket)
+    }
+
+    #[cfg(unix)]
+    fn host(&self) -> String {
+        PodmanApi::new().endpoint().host().to_string()
+    }
+
+    #[cfg(not(unix))]
+    fn host(&self) -> String {
+        "localhost".to_string()
+    }
 }
@@ -0,0 +1,635 @@
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use serde_json::{json, Value};
+use tracing::*;
+
+use crate::{
+    container::{Container, Exec, Image, ImageBuild, IntoContainer, Network, Volume},
+    error::{ContainerResult, ContainersError},
+    rt::{DetailedContainerInfo, ExecOutput},
+};
+
+use super::{
+    archive::archive_files,
+    docker_api::{
+        build_image_api, copy_from_api, copy_to_api, events_api, exec_api, exec_stream_api,
+        follow_progress, image_exists_api, label_map, pipe_log, prune_orphans_api, put_archive,
+        stop_timeout_query,
+    },
+    http::{encode, Endpoint},
+    reaper,
+    shared::{ensure_image, named_volumes, wait_for},
+    Client, ContainerHandle, EventFilter, Events, ExecStream, Log, NetworkHandle, Pruned,
+};
+
+///
+/// The PodmanApi struct is used for accessing the libpod api of podman directly, without spawning a podman process per request.
+///
+/// By default the api is expected on `CONTAINER_HOST`, falling back to the rootless socket at `$XDG_RUNTIME_DIR/podman/podman.sock`.
+/// The socket can be enabled by running `systemctl --user enable --now podman.socket`.
+///
+/// ```no_run
+/// use contain_rs_core::{
+///     client::{podman_api::PodmanApi, Client, Handle},
+///     container::{Container, Image, HealthCheck, WaitStrategy},
+/// };
+/// use std::str::FromStr;
+///
+/// let client = PodmanApi::new();
+///
+/// let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+///
+/// container.health_check(HealthCheck::new("curl http://localhost || exit 1"))
+///     .wait_for(WaitStrategy::HealthCheck);
+///
+/// client.run(&container).unwrap();
+/// client.wait(&container).unwrap();
+/// client.rm(&container).unwrap();
+/// ```
+///
+#[derive(Clone)]
+pub struct PodmanApi {
+    endpoint: Endpoint,
+}
+
+impl PodmanApi {
+    const API_PREFIX: &'static str = "/v4.0.0/libpod";
+    const ROOTFUL_SOCKET: &'static str = "/run/podman/podman.sock";
+
+    pub fn new() -> Self {
+        let host = env::var("CONTAINER_HOST").ok();
+
+        let endpoint = match host.as_deref().map(Endpoint::from_str) {
+            Some(Ok(endpoint)) => endpoint,
+            Some(Err(e)) => {
+                warn!(%e, "Ignoring CONTAINER_HOST");
+                Self::default_endpoint()
+            }
+            None => Self::default_endpoint(),
+        };
+
+        Self::from_endpoint(endpoint)
+    }
+
+    pub fn from_endpoint(endpoint: Endpoint) -> Self {
+        Self { endpoint }
+    }
+
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    fn default_endpoint() -> Endpoint {
+        let socket = env::var("XDG_RUNTIME_DIR")
+            .map(|dir| PathBuf::from(dir).join("podman").join("podman.sock"))
+            .unwrap_or_else(|_| PathBuf::from(Self::ROOTFUL_SOCKET));
+
+        Endpoint::Unix(socket)
+    }
+
+    fn path(path: &str) -> String {
+        format!("{}{path}", Self::API_PREFIX)
+    }
+
+    ///
+    /// Creates the named volumes of a container that do not exist yet, carrying the session labels.
+    ///
+    fn create_volumes(&self, container: &Container) -> ContainerResult<()> {
+        for name in named_volumes(container) {
+            let response = self.endpoint.request(
+                "GET",
+                &Self::path(&format!("/volumes/{}/json", encode(name))),
+                None,
+            )?;
+
+            if response.status != 404 {
+                response.error_for_status()?;
+                continue;
+            }
+
+            self.endpoint
+                .request(
+                    "POST",
+                    &Self::path("/volumes/create"),
+                    Some(&json!({ "Name": name, "Label": label_map(&[]) })),
+                )?
+                .error_for_status()?;
+        }
+
+        Ok(())
+    }
+
+    fn create_container(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &Self::path("/containers/create"),
+                Some(&build_spec(container)?),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+impl Default for PodmanApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client for PodmanApi {
+    type ClientType = PodmanApi;
+
+    fn command(&self) -> Command {
+        let mut command = Command::new("podman");
+
+        command.arg("--url").arg(self.endpoint.to_string());
+        command
+    }
+
+    fn create<C: IntoContainer>(&self, container: C) -> ContainerHandle<Self::ClientType> {
+        ContainerHandle::new(self.clone(), container.into_container())
+    }
+
+    fn run(&self, container: &Container) -> ContainerResult<()> {
+        ensure_image(self, container)?;
+        self.create_volumes(container)?;
+        self.create_container(container)?;
+
+        if !container.files.is_empty() {
+            put_archive(
+                &self.endpoint,
+                Self::API_PREFIX,
+                container,
+                "/",
+                &archive_files(&container.files)?,
+            )?;
+        }
+
+        self.endpoint
+            .request(
+                "POST",
+                &Self::path(&format!("/containers/{}/start", encode(&container.name))),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn stop(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request_within(
+                "POST",
+                &Self::path(&format!(
+                    "/containers/{}/stop{}",
+                    encode(&container.name),
+                    stop_timeout_query(container, "timeout")
+                )),
+                None,
+                container.stop_timeout.unwrap_or_default(),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn pause(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &Self::path(&format!("/containers/{}/pause", encode(&container.name))),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn unpause(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &Self::path(&format!("/containers/{}/unpause", encode(&container.name))),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn restart(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request_within(
+                "POST",
+                &Self::path(&format!(
+                    "/containers/{}/restart{}",
+                    encode(&container.name),
+                    stop_timeout_query(container, "t")
+                )),
+                None,
+                container.stop_timeout.unwrap_or_default(),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn kill(&self, container: &Container, signal: &str) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "POST",
+                &Self::path(&format!(
+                    "/containers/{}/kill?signal={}",
+                    encode(&container.name),
+                    encode(signal)
+                )),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn rm(&self, container: &Container) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "DELETE",
+                &Self::path(&format!(
+                    "/containers/{}?force=true",
+                    encode(&container.name)
+                )),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn log(&self, container: &Container) -> ContainerResult<Option<Log>> {
+        if self.exists(container)? {
+            let (response, connection) = self.endpoint.stream(
+                "GET",
+                &Self::path(&format!(
+                    "/containers/{}/logs?follow=true&stdout=true&stderr=true",
+                    encode(&container.name)
+                )),
+                None,
+            )?;
+
+            Ok(Some(pipe_log(
+                response.error_for_status()?.into_reader(),
+                connection,
+            )?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn inspect(&self, container: &Container) -> ContainerResult<Option<DetailedContainerInfo>> {
+        let response = self.endpoint.request(
+            "GET",
+            &Self::path(&format!("/containers/{}/json", encode(&container.name))),
+            None,
+        )?;
+
+        match response.status {
+            404 => Ok(None),
+            _ => {
+                let info: DetailedContainerInfo = response.error_for_status()?.json()?;
+
+                debug!(?info, "Inspect container");
+
+                Ok(Some(info))
+            }
+        }
+    }
+
+    fn exists(&self, container: &Container) -> ContainerResult<bool> {
+        Ok(self.inspect(container)?.is_some())
+    }
+
+    fn runs(&self, container: &Container) -> ContainerResult<bool> {
+        match self.inspect(container)? {
+            Some(detail) => Ok(detail.state.running),
+            None => Ok(false),
+        }
+    }
+
+    fn wait(&self, container: &Container) -> ContainerResult<()> {
+        wait_for(self, container)
+    }
+
+    #[instrument(skip_all, fields(%image))]
+    fn pull(&self, image: &Image) -> ContainerResult<()> {
+        let path = Self::path(&format!(
+            "/images/pull?reference={}",
+            encode(&String::from(image))
+        ));
+
+        let (response, _) = self.endpoint.stream("POST", &path, None)?;
+
+        follow_progress(response.error_for_status()?.into_reader())
+    }
+
+    fn build_image<F: FnMut(&str)>(&self, build: &ImageBuild, output: F) -> ContainerResult<Image> {
+        build_image_api(&self.endpoint, Self::API_PREFIX, build, output)
+    }
+
+    fn image_exists(&self, image: &Image) -> ContainerResult<bool> {
+        image_exists_api(&self.endpoint, Self::API_PREFIX, image)
+    }
+
+    fn copy_to(
+        &self,
+        container: &Container,
+        host_path: &Path,
+        container_path: &str,
+    ) -> ContainerResult<()> {
+        copy_to_api(
+            &self.endpoint,
+            Self::API_PREFIX,
+            container,
+            host_path,
+            container_path,
+        )
+    }
+
+    fn copy_from(
+        &self,
+        container: &Container,
+        container_path: &str,
+        host_path: &Path,
+    ) -> ContainerResult<()> {
+        copy_from_api(
+            &self.endpoint,
+            Self::API_PREFIX,
+            container,
+            container_path,
+            host_path,
+        )
+    }
+
+    fn exec(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecOutput> {
+        exec_api(&self.endpoint, Self::API_PREFIX, container, exec)
+    }
+
+    fn exec_stream(&self, container: &Container, exec: &Exec) -> ContainerResult<ExecStream> {
+        exec_stream_api(&self.endpoint, Self::API_PREFIX, container, exec)
+    }
+
+    fn events(&self, filter: &EventFilter) -> ContainerResult<Events> {
+        events_api(&self.endpoint, Self::API_PREFIX, filter)
+    }
+
+    fn network(&self, network: Network) -> NetworkHandle<Self::ClientType> {
+        NetworkHandle {
+            client: self.clone(),
+            network,
+        }
+    }
+
+    fn create_network(&self, network: &Network) -> ContainerResult<()> {
+        let mut body = json!({
+            "name": network.name,
+            "internal": network.internal,
+            "labels": label_map(&network.labels),
+        });
+
+        if let Some(driver) = &network.driver {
+            body["driver"] = json!(driver);
+        }
+
+        self.endpoint
+            .request("POST", &Self::path("/networks/create"), Some(&body))?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn rm_network(&self, network: &Network) -> ContainerResult<()> {
+        self.endpoint
+            .request(
+                "DELETE",
+                &Self::path(&format!("/networks/{}", encode(&network.name))),
+                None,
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn network_exists(&self, network: &Network) -> ContainerResult<bool> {
+        let response = self.endpoint.request(
+            "GET",
+            &Self::path(&format!("/networks/{}/json", encode(&network.name))),
+            None,
+        )?;
+
+        match response.status {
+            404 => Ok(false),
+            _ => {
+                response.error_for_status()?;
+                Ok(true)
+            }
+        }
+    }
+
+    fn connect_network(
+        &self,
+        network: &Network,
+        container: &Container,
+        aliases: &[String],
+    ) -> ContainerResult<()> {
+        let body = json!({
+            "container": container.name,
+            "aliases": aliases,
+        });
+
+        self.endpoint
+            .request(
+                "POST",
+                &Self::path(&format!("/networks/{}/connect", encode(&network.name))),
+                Some(&body),
+            )?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    fn prune_orphans(&self) -> ContainerResult<Pruned> {
+        prune_orphans_api(&self.endpoint, Self::API_PREFIX, "/networks/json")
+    }
+
+    fn start_reaper(&self) -> ContainerResult<()> {
+        reaper::ensure_started(self, reaper::socket(&self.endpoint))
+    }
+
+    fn host(&self) -> String {
+        self.endpoint.host().to_string()
+    }
+}
+
+///
+/// Builds the libpod spec generator for a container, the api equivalent of [super::shared::build_run_command].
+///
+pub(crate) fn build_spec(container: &Container) -> ContainerResult<Value> {
+    let env: HashMap<&str, &str> = container
+        .env_vars
+        .iter()
+        .map(|env_var| (env_var.key.as_str(), env_var.value.as_str()))
+        .collect();
+
+    let mappings = container
+        .port_mappings
+        .iter()
+        .map(|mapping| (&mapping.target, Some(&mapping.source)));
+    let exposed = container.exposed_ports.iter().map(|port| (port, None));
+
+    let port_mappings = mappings
+        .chain(exposed)
+        .map(|(target, source)| {
+            let (port, protocol) = split_protocol(&target.number);
+
+            // libpod picks a random host port for 0
+            let host_port = match source {
+                Some(source) => parse_port(split_protocol(&source.number).0)?,
+                None => 0,
+            };
+
+            Ok(json!({
+                "host_port": host_port,
+                "container_port": parse_port(port)?,
+                "protocol": protocol,
+            }))
+        })
+        .collect::<ContainerResult<Vec<Value>>>()?;
+
+    let mut mounts = Vec::new();
+    let mut volumes = Vec::new();
+
+    container.volumes.iter().for_each(|volume| match volume {
+        Volume::Mount {
+            host_path,
+            mount_point,
+        } => mounts.push(json!({
+            "type": "bind",
+            "source": host_path,
+            "destination": mount_point,
+            "options": ["rbind"],
+        })),
+        Volume::Named { name, mount_point } => volumes.push(json!({
+            "Name": name,
+            "Dest": mount_point,
+        })),
+    });
+
+    let mut spec = json!({
+        "name": container.name,
+        "image": String::from(&container.image),
+        "env": env,
+        "labels": label_map(&container.labels),
+        "portmappings": port_mappings,
+        "publish_image_ports": container.publish_all_ports,
+        "remove": container.auto_remove,
+        "mounts": mounts,
+        "volumes": volumes,
+    });
+
+    if !container.command.is_empty() {
+        spec["command"] = json!(container.command);
+    }
+
+    if let Some(network) = &container.network {
+        spec["netns"] = json!({ "nsmode": "bridge" });
+        spec["Networks"] = json!({
+            &network.name: { "aliases": container.network_aliases }
+        });
+    }
+
+    if let Some(check) = &container.health_check {
+        let mut health_config = json!({ "Test": ["CMD-SHELL", check.command] });
+
+        if let Some(start_period) = check.start_period {
+            health_config["StartPeriod"] = json!(start_period.as_nanos() as u64);
+        }
+
+        if let Some(interval) = check.interval {
+            health_config["Interval"] = json!(interval.as_nanos() as u64);
+        }
+
+        if let Some(timeout) = check.timeout {
+            health_config["Timeout"] = json!(timeout.as_nanos() as u64);
+        }
+
+        if let Some(retries) = check.retries {
+            health_config["Retries"] = json!(retries);
+        }
+
+        spec["healthconfig"] = health_config;
+    }
+
+    let resources = &container.resources;
+    let mut resource_limits = serde_json::Map::new();
+
+    if resources.memory.is_some() || resources.memory_swap.is_some() {
+        resource_limits.insert(
+            "memory".into(),
+            json!({ "limit": resources.memory, "swap": resources.memory_swap }),
+        );
+    }
+
+    if let Some(cpus) = resources.cpus {
+        // libpod takes a quota per scheduling period, like the cli does for --cpus
+        resource_limits.insert(
+            "cpu".into(),
+            json!({ "quota": (cpus * CPU_PERIOD as f64).round() as i64, "period": CPU_PERIOD }),
+        );
+    }
+
+    if let Some(pids_limit) = resources.pids_limit {
+        resource_limits.insert("pids".into(), json!({ "limit": pids_limit }));
+    }
+
+    if !resource_limits.is_empty() {
+        spec["resource_limits"] = Value::Object(resource_limits);
+    }
+
+    if !resources.ulimits.is_empty() {
+        let r_limits: Vec<Value> = resources
+            .ulimits
+            .iter()
+            .map(|ulimit| {
+                json!({
+                    "type": format!("RLIMIT_{}", ulimit.name.to_uppercase()),
+                    "soft": ulimit.soft,
+                    "hard": ulimit.hard,
+                })
+            })
+            .collect();
+
+        spec["r_limits"] = json!(r_limits);
+    }
+
+    Ok(spec)
+}
+
+/// The cfs scheduling period in microseconds, cpu limits are expressed as quota of it.
+const CPU_PERIOD: u64 = 100_000;
+
+fn split_protocol(port: &str) -> (&str, &str) {
+    port.split_once('/').unwrap_or((port, "tcp"))
+}
+
+fn parse_port(port: &str) -> ContainerResult<u16> {
+    port.parse().map_err(|_| ContainersError::InvalidPort {
+        port: port.to_string(),
+    })
+}
@@ -0,0 +1,206 @@
+//!
+//! Removes everything a session created, even when the process gets killed before it can clean up.
+//!
+//! Dropping a [ContainerHandle](super::ContainerHandle) does not help when the process is killed with `SIGKILL`
+//! or aborts on a panic. The reaper is a [ryuk](https://github.com/testcontainers/moby-ryuk) sidecar container
+//! that has access to the socket of the runtime. contain-rs holds a connection to it and registers the
+//! [session label](crate::session::SESSION_LABEL). Once the connection drops, because the process is gone,
+//! the reaper removes all containers, networks and volumes carrying the label of the session.
+//!
+//! The reaper is opt-in, see [Client::start_reaper].
+//!
+//! ```no_run
+//! use contain_rs_core::client::{docker::Docker, Client};
+//!
+//! let client = Docker::new();
+//!
+//! client.start_reaper().unwrap();
+//! ```
+//!
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use tracing::*;
+
+use crate::{
+    container::{Container, Image},
+    error::{ContainerResult, ContainersError},
+    session::{session_id, REAPER_LABEL, SESSION_LABEL},
+};
+
+use super::{http::Endpoint, Client};
+
+pub const REAPER_IMAGE: &str = "docker.io/testcontainers/ryuk:0.11.0";
+pub const REAPER_PORT: u16 = 8080;
+
+pub(crate) const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+#[cfg(not(unix))]
+pub(crate) const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The reaper of a runtime, empty until it has been started.
+type ReaperSlot = Arc<Mutex<Option<Reaper>>>;
+
+lazy_static! {
+    /// The reapers of the process, by the host and socket of the runtime they clean up.
+    ///
+    /// Every runtime has a slot of its own, so starting one reaper does not hold up the others.
+    static ref REAPERS: Mutex<HashMap<(String, PathBuf), ReaperSlot>> = Mutex::new(HashMap::new());
+}
+
+///
+/// A connection to a reaper, the session gets removed once it is dropped.
+///
+#[derive(Debug)]
+pub struct Reaper {
+    connection: TcpStream,
+}
+
+impl Reaper {
+    ///
+    /// Connects to a reaper listening on `address` and registers the current session.
+    ///
+    /// Anything speaking the ryuk protocol works, the session is sent as `label=contain-rs.session=<id>`
+    /// and has to be acknowledged with `ACK`.
+    ///
+    pub fn connect(address: impl ToSocketAddrs) -> ContainerResult<Self> {
+        let connection = TcpStream::connect(address)?;
+
+        connection.set_read_timeout(Some(ACK_TIMEOUT))?;
+        (&connection).write_all(format!("label={SESSION_LABEL}={}\n", session_id()).as_bytes())?;
+
+        let mut ack = String::new();
+        BufReader::new(&connection).read_line(&mut ack)?;
+
+        if ack.trim_end() != "ACK" {
+            return Err(ContainersError::ReaperFailed {
+                message: format!("unexpected answer to the session filter: {ack:?}"),
+            });
+        }
+
+        connection.set_read_timeout(None)?;
+
+        debug!(session = session_id(), "Connected to reaper");
+
+        Ok(Self { connection })
+    }
+
+    ///
+    /// The sidecar container, with the runtime socket at `socket` mounted.
+    ///
+    /// It is removed by the runtime once ryuk exits after cleaning up the session.
+    ///
+    pub fn container(socket: impl AsRef<Path>) -> Container {
+        let mut container = Container::from_image(Image::from_str(REAPER_IMAGE).unwrap());
+
+        container
+            .name(&format!("contain-rs-reaper-{}", session_id()))
+            .mount(&socket.as_ref().to_string_lossy(), DOCKER_SOCKET)
+            .expose_port(REAPER_PORT)
+            .auto_remove(true)
+            .label(REAPER_LABEL, "true");
+
+        container
+    }
+
+    ///
+    /// Runs the [sidecar container](Reaper::container) and connects to it once it accepts connections.
+    ///
+    /// The reaper is reached on the [host](Client::host) of the runtime.
+    ///
+    pub fn start<C: Client>(client: &C, socket: impl AsRef<Path>) -> ContainerResult<Self> {
+        let container = Self::container(socket);
+
+        if !client.runs(&container)? {
+            if client.exists(&container)? {
+                client.rm(&container)?;
+            }
+
+            client.run(&container)?;
+        }
+
+        let port = client
+            .inspect(&container)?
+            .and_then(|info| info.host_port(REAPER_PORT))
+            .ok_or_else(|| ContainersError::ReaperFailed {
+                message: format!("port {REAPER_PORT} of {} is not published", container.name),
+            })?;
+
+        let deadline = Instant::now() + container.startup_timeout;
+
+        // the port might be published before ryuk listens on it
+        loop {
+            match Self::connect((client.host().as_str(), port)) {
+                Ok(reaper) => return Ok(reaper),
+                Err(e) if Instant::now() < deadline => {
+                    trace!(%e, "Reaper is not ready yet");
+                    thread::sleep(RETRY_INTERVAL);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Drop for Reaper {
+    fn drop(&mut self) {
+        debug!(session = session_id(), "Disconnecting from reaper");
+        let _ = self.connection.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+///
+/// The runtime socket to mount into the reaper, the default docker socket for tcp endpoints.
+///
+pub(crate) fn socket(endpoint: &Endpoint) -> PathBuf {
+    match endpoint {
+        #[cfg(unix)]
+        Endpoint::Unix(path) => path.clone(),
+        Endpoint::Tcp(_) => PathBuf::from(DOCKER_SOCKET),
+    }
+}
+
+///
+/// Starts the reaper of the process for the runtime of the client, unless it is running already.
+///
+/// Every runtime, told apart by its host and socket, gets a reaper of its own.
+/// The connections are kept until the process exits.
+///
+/// Only callers for the same runtime wait for each other while the reaper starts,
+/// a failed start is tried again by the next caller.
+///
+pub(crate) fn ensure_started<C: Client>(
+    client: &C,
+    socket: impl AsRef<Path>,
+) -> ContainerResult<()> {
+    let key = (client.host(), socket.as_ref().to_path_buf());
+    let slot = lock(&REAPERS).entry(key).or_default().clone();
+    let mut reaper = lock(&slot);
+
+    if reaper.is_none() {
+        *reaper = Some(Reaper::start(client, socket)?);
+    }
+
+    Ok(())
+}
+
+///
+/// Locks a mutex, even when a thread panicked while holding it.
+///
+/// The reapers stay valid in that case, a panicking start leaves its slot empty.
+///
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
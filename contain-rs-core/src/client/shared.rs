@@ -1,31 +1,179 @@
 use std::{
-    io::BufRead,
+    env, fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
     process::{Command, Output, Stdio},
+    sync::mpsc,
     thread,
-    time::Duration,
 };
 
-use regex::Regex;
 use tracing::*;
 
 use crate::{
-    container::{Container, Volume, WaitStrategy},
+    container::{
+        gen_hash, BuildContext, Container, Exec, Image, ImageBuild, Label, Network, PullPolicy,
+        Volume,
+    },
     error::{ContainerResult, ContainersError},
-    rt::{ContainerStatus, DetailedContainerInfo},
+    rt::{DetailedContainerInfo, ExecOutput, ImageInfo, NetworkInfo},
+    session::{is_orphan, with_session_labels, PID_LABEL},
 };
 
-use super::{Client, Log};
+use super::{
+    archive::{archive_files, unpack},
+    wait::{self, Cancel},
+    Client, EventFilter, Events, ExecStream, Log, Pruned,
+};
 
 pub fn run_and_wait_for_command(command: &mut Command) -> ContainerResult<String> {
     let output = try_run_and_wait_for_command(command)?;
 
+    command_stdout(command, output)
+}
+
+///
+/// Returns the stdout of a finished command, or an error in case it did not exit successfully.
+///
+pub fn command_stdout(command: &Command, output: Output) -> ContainerResult<String> {
     if let Some(0) = output.status.code() {
-        Ok(String::from_utf8(output.stdout).unwrap())
+        Ok(String::from_utf8(output.stdout)?)
+    } else {
+        Err(command_error(command, output))
+    }
+}
+
+///
+/// Converts the output of a failed command into an error.
+///
+/// Well known daemon errors are reported as their own [ContainersError] variant,
+/// anything else ends up in a [ContainersError::CommandError].
+///
+pub fn command_error(command: &Command, output: Output) -> ContainersError {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+    daemon_error(&stderr).unwrap_or_else(|| ContainersError::CommandError {
+        command: command_line(command),
+        status: output.status,
+        stdout: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        stderr,
+    })
+}
+
+///
+/// Classifies a well known error message of a runtime, be it printed by a cli or returned by an engine api.
+///
+pub(crate) fn daemon_error(message: &str) -> Option<ContainersError> {
+    let lowercase = message.to_lowercase();
+    let message = message.to_string();
+
+    let contains_any = |patterns: &[&str]| patterns.iter().any(|p| lowercase.contains(p));
+
+    if contains_any(&[
+        "cannot connect to the docker daemon",
+        "error during connect",
+        "unable to connect to podman",
+        "cannot connect to podman",
+    ]) {
+        Some(ContainersError::DaemonUnreachable { message })
+    } else if contains_any(&["port is already allocated", "address already in use"]) {
+        Some(ContainersError::PortAlreadyAllocated { message })
+    } else if lowercase.contains("container name") && lowercase.contains("already in use") {
+        Some(ContainersError::NameConflict { message })
+    } else if contains_any(&[
+        "no such image",
+        "image not known",
+        "manifest unknown",
+        "pull access denied",
+        "repository does not exist",
+        "repository name not known",
+    ]) {
+        Some(ContainersError::ImageNotFound { message })
+    } else if contains_any(&["no such container", "no container with name or id"]) {
+        // docker names the container after a colon, podman in quotes
+        let container_name = message
+            .split('"')
+            .nth(1)
+            .or_else(|| message.rsplit_once(": ").map(|(_, name)| name))
+            .unwrap_or(&message)
+            .trim()
+            .to_string();
+
+        Some(ContainersError::ContainerNotExists { container_name })
     } else {
-        Err(ContainersError::CommandError(output))
+        None
+    }
+}
+
+///
+/// Converts an error spawning a command, reporting a missing binary as [ContainersError::RuntimeNotFound].
+///
+pub fn spawn_error(command: &Command, error: io::Error) -> ContainersError {
+    match error.kind() {
+        io::ErrorKind::NotFound => ContainersError::RuntimeNotFound {
+            binary: command.get_program().to_string_lossy().into_owned(),
+        },
+        _ => ContainersError::IOError(error),
     }
 }
 
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[instrument(skip_all)]
+pub fn run_and_wait_for_command_with_input(
+    command: &mut Command,
+    input: &[u8],
+) -> ContainerResult<String> {
+    debug!(?command, "Running command");
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(command, e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // a failing command may close stdin early, its exit code tells what went wrong
+        if let Err(e) = stdin.write_all(input) {
+            debug!(%e, "Failed writing to stdin");
+        }
+    }
+
+    command_stdout(command, child.wait_with_output()?)
+}
+
+///
+/// Runs a container that has [files](Container::files) to be copied into it.
+///
+/// The container gets created first, then the files are copied and finally the container is started.
+///
+pub fn run_with_files(
+    new_command: impl Fn() -> Command,
+    container: &Container,
+) -> ContainerResult<()> {
+    let archive = archive_files(&container.files)?;
+
+    let mut create = new_command();
+    build_create_command(&mut create, container);
+    run_and_wait_for_command(&mut create)?;
+
+    let mut copy = new_command();
+    build_copy_archive_command(&mut copy, container);
+    run_and_wait_for_command_with_input(&mut copy, &archive)?;
+
+    let mut start = new_command();
+    build_start_command(&mut start, container);
+    run_and_wait_for_command(&mut start)?;
+
+    Ok(())
+}
+
 #[instrument(skip_all)]
 pub fn try_run_and_wait_for_command(command: &mut Command) -> ContainerResult<Output> {
     debug!(?command, "Running command");
@@ -34,11 +182,53 @@ pub fn try_run_and_wait_for_command(command: &mut Command) -> ContainerResult<Ou
         .stdout(Stdio::piped()) // TODO fm - Sometimes podman asks the user for which repo to use. This is currently ignored.
         .stderr(Stdio::piped())
         .spawn()
-        .unwrap();
+        .map_err(|e| spawn_error(command, e))?;
 
     Ok(child.wait_with_output()?)
 }
 
+pub fn build_pull_command<'a>(command: &'a mut Command, image: &Image) -> &'a Command {
+    command.arg("pull").arg(String::from(image))
+}
+
+pub fn build_image_inspect_command<'a>(command: &'a mut Command, image: &Image) -> &'a Command {
+    command.arg("image").arg("inspect").arg(String::from(image))
+}
+
+pub fn build_image_build_command<'a>(
+    command: &'a mut Command,
+    build: &ImageBuild,
+    context: &Path,
+    tags: &[Image],
+) -> &'a Command {
+    command.arg("build");
+
+    // the cli resolves the dockerfile relative to the working directory, not the context
+    if let Some(dockerfile) = &build.dockerfile {
+        command.arg("-f").arg(context.join(dockerfile));
+    }
+
+    build.build_args.iter().for_each(|build_arg| {
+        command
+            .arg("--build-arg")
+            .arg(format!("{}={}", build_arg.key, build_arg.value));
+    });
+
+    if let Some(target) = &build.target {
+        command.arg("--target").arg(target);
+    }
+
+    if build.no_cache {
+        command.arg("--no-cache");
+    }
+
+    tags.iter().for_each(|tag| {
+        command.arg("-t").arg(String::from(tag));
+    });
+
+    command.arg(context)
+}
+
 pub fn build_log_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
     command.arg("logs").arg("-f").arg(&container.name)
 }
@@ -48,20 +238,115 @@ pub fn build_rm_command<'a>(command: &'a mut Command, container: &Container) ->
 }
 
 pub fn build_stop_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
-    command.arg("stop").arg(&container.name)
+    command.arg("stop");
+    add_stop_timeout_arg(command, container);
+
+    command.arg(&container.name)
+}
+
+pub fn build_restart_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
+    command.arg("restart");
+    add_stop_timeout_arg(command, container);
+
+    command.arg(&container.name)
+}
+
+pub fn build_pause_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
+    command.arg("pause").arg(&container.name)
+}
+
+pub fn build_unpause_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
+    command.arg("unpause").arg(&container.name)
+}
+
+pub fn build_kill_command<'a>(
+    command: &'a mut Command,
+    container: &Container,
+    signal: &str,
+) -> &'a Command {
+    command
+        .arg("kill")
+        .arg("--signal")
+        .arg(signal)
+        .arg(&container.name)
+}
+
+fn add_stop_timeout_arg(command: &mut Command, container: &Container) {
+    if let Some(timeout) = container.stop_timeout_secs() {
+        command.arg("-t").arg(timeout.to_string());
+    }
 }
 
 pub fn build_run_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
     add_run_args(command);
+    add_container_args(command, container);
+
+    command
+}
+
+///
+/// Creates the container without starting it, see [build_start_command].
+///
+pub fn build_create_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
+    command.arg("create");
+    add_container_args(command, container);
+
+    command
+}
+
+pub fn build_start_command<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
+    command.arg("start").arg(&container.name)
+}
+
+pub fn build_copy_to_command<'a>(
+    command: &'a mut Command,
+    container: &Container,
+    host_path: &Path,
+    container_path: &str,
+) -> &'a Command {
+    command
+        .arg("cp")
+        .arg(host_path)
+        .arg(format!("{}:{container_path}", container.name))
+}
+
+pub fn build_copy_from_command<'a>(
+    command: &'a mut Command,
+    container: &Container,
+    container_path: &str,
+    host_path: &Path,
+) -> &'a Command {
+    command
+        .arg("cp")
+        .arg(format!("{}:{container_path}", container.name))
+        .arg(host_path)
+}
+
+///
+/// Copies a tar archive read from stdin into the container, extracting it at `/`.
+///
+pub fn build_copy_archive_command<'a>(
+    command: &'a mut Command,
+    container: &Container,
+) -> &'a Command {
+    command
+        .arg("cp")
+        .arg("-")
+        .arg(format!("{}:/", container.name))
+}
+
+fn add_container_args(command: &mut Command, container: &Container) {
     add_name_arg(command, container);
+    add_auto_remove_arg(command, container);
     add_env_var_args(command, container);
+    add_label_args(command, &container.labels);
     add_volume_args(command, container);
+    add_network_args(command, container);
     add_export_ports_args(command, container);
     add_health_check_args(command, container);
+    add_resource_args(command, container);
     add_image_arg(command, container);
     add_command_arg(command, container);
-
-    command
 }
 
 fn add_volume_args<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
@@ -79,6 +364,16 @@ fn add_volume_args<'a>(command: &'a mut Command, container: &Container) -> &'a C
     folded
 }
 
+fn add_network_args(command: &mut Command, container: &Container) {
+    if let Some(network) = &container.network {
+        command.arg("--network").arg(&network.name);
+
+        container.network_aliases.iter().for_each(|alias| {
+            command.arg("--network-alias").arg(alias);
+        });
+    }
+}
+
 fn add_command_arg<'a>(command: &'a mut Command, container: &Container) -> &'a Command {
     let folded = container
         .command
@@ -96,10 +391,124 @@ pub fn build_inspect_command<'a>(command: &'a mut Command, container: &Container
     command.arg("inspect").arg(&container.name)
 }
 
+pub fn build_exec_command<'a>(
+    command: &'a mut Command,
+    container: &Container,
+    exec: &Exec,
+) -> &'a Command {
+    command.arg("exec");
+
+    exec.env_vars.iter().for_each(|env_var| {
+        command
+            .arg("-e")
+            .arg(format!("{}={}", env_var.key, env_var.value));
+    });
+
+    if let Some(workdir) = &exec.workdir {
+        command.arg("-w").arg(workdir);
+    }
+
+    if let Some(user) = &exec.user {
+        command.arg("-u").arg(user);
+    }
+
+    command.arg(&container.name).args(&exec.command)
+}
+
+pub fn build_events_command<'a>(command: &'a mut Command, filter: &EventFilter) -> &'a Command {
+    command
+        .arg("events")
+        .arg("--format")
+        .arg("{{json .}}")
+        .arg("--filter")
+        .arg("type=container");
+
+    filter.containers.iter().for_each(|container| {
+        command
+            .arg("--filter")
+            .arg(format!("container={container}"));
+    });
+
+    filter.labels.iter().for_each(|label| {
+        command
+            .arg("--filter")
+            .arg(format!("label={}={}", label.key, label.value));
+    });
+
+    if let Some(since) = filter.since_timestamp() {
+        command.arg("--since").arg(since.to_string());
+    }
+
+    command
+}
+
+pub fn build_network_create_command<'a>(
+    command: &'a mut Command,
+    network: &Network,
+) -> &'a Command {
+    command.arg("network").arg("create");
+
+    if let Some(driver) = &network.driver {
+        command.arg("--driver").arg(driver);
+    }
+
+    if network.internal {
+        command.arg("--internal");
+    }
+
+    add_label_args(command, &network.labels);
+
+    command.arg(&network.name)
+}
+
+pub fn build_network_rm_command<'a>(command: &'a mut Command, network: &Network) -> &'a Command {
+    command.arg("network").arg("rm").arg(&network.name)
+}
+
+pub fn build_volume_inspect_command<'a>(command: &'a mut Command, name: &str) -> &'a Command {
+    command.arg("volume").arg("inspect").arg(name)
+}
+
+pub fn build_volume_create_command<'a>(command: &'a mut Command, name: &str) -> &'a Command {
+    command.arg("volume").arg("create");
+
+    add_label_args(command, &[]);
+
+    command.arg(name)
+}
+
+pub fn build_network_inspect_command<'a>(
+    command: &'a mut Command,
+    network: &Network,
+) -> &'a Command {
+    command.arg("network").arg("inspect").arg(&network.name)
+}
+
+pub fn build_network_connect_command<'a>(
+    command: &'a mut Command,
+    network: &Network,
+    container: &Container,
+    aliases: &[String],
+) -> &'a Command {
+    command.arg("network").arg("connect");
+
+    aliases.iter().for_each(|alias| {
+        command.arg("--alias").arg(alias);
+    });
+
+    command.arg(&network.name).arg(&container.name)
+}
+
 fn add_run_args(command: &mut Command) {
     command.arg("run").arg("-d");
 }
 
+fn add_auto_remove_arg(command: &mut Command, container: &Container) {
+    if container.auto_remove {
+        command.arg("--rm");
+    }
+}
+
 fn add_env_var_args(command: &mut Command, container: &Container) {
     container.env_vars.iter().for_each(|env_var| {
         command
@@ -108,6 +517,17 @@ fn add_env_var_args(command: &mut Command, container: &Container) {
     });
 }
 
+///
+/// Adds the given labels followed by the [session labels](crate::session::session_labels).
+///
+fn add_label_args(command: &mut Command, labels: &[Label]) {
+    with_session_labels(labels).iter().for_each(|label| {
+        command
+            .arg("--label")
+            .arg(format!("{}={}", label.key, label.value));
+    });
+}
+
 fn add_health_check_args(command: &mut Command, container: &Container) {
     if let Some(check) = &container.health_check {
         command.arg("--health-cmd").arg(&check.command);
@@ -130,6 +550,32 @@ fn add_health_check_args(command: &mut Command, container: &Container) {
     }
 }
 
+fn add_resource_args(command: &mut Command, container: &Container) {
+    let resources = &container.resources;
+
+    if let Some(memory) = resources.memory {
+        command.arg(format!("--memory={memory}"));
+    }
+
+    if let Some(memory_swap) = resources.memory_swap {
+        command.arg(format!("--memory-swap={memory_swap}"));
+    }
+
+    if let Some(cpus) = resources.cpus {
+        command.arg(format!("--cpus={cpus}"));
+    }
+
+    if let Some(pids_limit) = resources.pids_limit {
+        command.arg(format!("--pids-limit={pids_limit}"));
+    }
+
+    resources.ulimits.iter().for_each(|ulimit| {
+        command
+            .arg("--ulimit")
+            .arg(format!("{}={}:{}", ulimit.name, ulimit.soft, ulimit.hard));
+    });
+}
+
 fn add_image_arg(command: &mut Command, container: &Container) {
     command.arg(String::from(&container.image));
 }
@@ -140,9 +586,263 @@ fn add_export_ports_args(command: &mut Command, container: &Container) {
             "-p{}:{}",
             port_mapping.source.number, port_mapping.target.number
         ));
+    });
+
+    container.exposed_ports.iter().for_each(|port| {
+        command.arg(format!("-p{}", port.number));
+    });
+
+    if container.publish_all_ports {
+        command.arg("-P");
+    }
+}
+
+///
+/// Pulls an image, reporting every line of progress the runtime prints as a tracing event.
+///
+#[instrument(skip_all, fields(%image))]
+pub fn pull(command: &mut Command, image: &Image) -> ContainerResult<()> {
+    build_pull_command(command, image);
+
+    debug!(?command, "Running command");
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(command, e))?;
+
+    // podman reports progress on stderr, docker on stdout
+    let stderr = child
+        .stderr
+        .take()
+        .map(|stderr| thread::spawn(move || report_progress(stderr)));
+    let stdout = child.stdout.take().map(report_progress).unwrap_or_default();
+    let stderr = stderr
+        .and_then(|stderr| stderr.join().ok())
+        .unwrap_or_default();
+
+    let output = Output {
+        status: child.wait()?,
+        stdout,
+        stderr,
+    };
+
+    command_stdout(command, output)?;
+
+    Ok(())
+}
+
+fn report_progress(stream: impl Read) -> Vec<u8> {
+    let mut output = Vec::new();
+
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        info!(progress = line.trim(), "Pulling image");
+
+        output.extend_from_slice(line.as_bytes());
+        output.push(b'\n');
+    }
+
+    output
+}
+
+///
+/// Builds an image, passing every line of output to `output` while the build is running.
+///
+/// Archive contexts are unpacked into a temporary directory first, as not every cli can read them from stdin.
+///
+#[instrument(skip_all)]
+pub fn build_image(
+    command: &mut Command,
+    build: &ImageBuild,
+    mut output: impl FnMut(&str),
+) -> ContainerResult<Image> {
+    let mut tags = build.tags_or_generated();
+
+    match &build.context {
+        BuildContext::Path(context) => run_build(command, build, context, &tags, &mut output)?,
+        BuildContext::Archive(archive) => {
+            let context = env::temp_dir().join(format!("contain-rs-build-{}", gen_hash()));
+
+            let result = unpack(archive, &context)
+                .and_then(|_| run_build(command, build, &context, &tags, &mut output));
+
+            if let Err(e) = fs::remove_dir_all(&context) {
+                warn!(%e, context = %context.display(), "Failed to remove build context");
+            }
+
+            result?;
+        }
+    }
+
+    Ok(tags.swap_remove(0))
+}
+
+fn run_build(
+    command: &mut Command,
+    build: &ImageBuild,
+    context: &Path,
+    tags: &[Image],
+    output: &mut impl FnMut(&str),
+) -> ContainerResult<()> {
+    build_image_build_command(command, build, context, tags);
+
+    debug!(?command, "Running command");
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(command, e))?;
+
+    let (sender, lines) = mpsc::channel();
+
+    let streams: [Option<Box<dyn Read + Send>>; 2] = [
+        child.stdout.take().map(|stdout| Box::new(stdout) as _),
+        child.stderr.take().map(|stderr| Box::new(stderr) as _),
+    ];
+
+    // forward both streams, so the build does not block on a full pipe
+    streams.into_iter().flatten().for_each(|stream| {
+        let sender = sender.clone();
+
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+    });
+
+    drop(sender);
+
+    let mut log = Vec::new();
+
+    for line in lines {
+        output(&line);
+
+        log.extend_from_slice(line.as_bytes());
+        log.push(b'\n');
+    }
+
+    let build_output = Output {
+        status: child.wait()?,
+        stdout: Vec::new(),
+        stderr: log,
+    };
+
+    command_stdout(command, build_output)?;
+
+    Ok(())
+}
+
+#[instrument(skip_all, fields(%image))]
+pub fn image_exists(command: &mut Command, image: &Image) -> ContainerResult<bool> {
+    build_image_inspect_command(command, image);
+
+    let output = try_run_and_wait_for_command(command)?;
+
+    if output.status.success() {
+        return Ok(true);
+    }
+
+    match command_error(command, output) {
+        ContainersError::ImageNotFound { .. } => Ok(false),
+        e => Err(e),
+    }
+}
+
+///
+/// The manifest digest of an image, none if the image is gone or was never in a registry.
+///
+pub fn image_digest(command: &mut Command, image_id: &str) -> ContainerResult<Option<String>> {
+    if image_id.is_empty() {
+        return Ok(None);
+    }
+
+    command.arg("image").arg("inspect").arg(image_id);
+
+    let output = try_run_and_wait_for_command(command)?;
+
+    if !output.status.success() {
+        return match command_error(command, output) {
+            ContainersError::ImageNotFound { .. } => Ok(None),
+            e => Err(e),
+        };
+    }
+
+    let infos: Vec<ImageInfo> = serde_json::from_slice(&output.stdout)?;
+
+    Ok(infos
+        .first()
+        .and_then(ImageInfo::digest)
+        .map(ToString::to_string))
+}
+
+///
+/// The names of the named volumes of a container.
+///
+pub(crate) fn named_volumes(container: &Container) -> impl Iterator<Item = &str> {
+    container.volumes.iter().filter_map(|volume| match volume {
+        Volume::Named { name, .. } => Some(name.as_str()),
+        Volume::Mount { .. } => None,
     })
 }
 
+///
+/// Creates the named volumes of a container that do not exist yet, carrying the session labels.
+///
+/// The runtimes would create them along with the container, but without labels the
+/// [reaper](super::reaper) could not remove them. Existing volumes are left as they are.
+///
+pub fn ensure_volumes(
+    new_command: impl Fn() -> Command,
+    container: &Container,
+) -> ContainerResult<()> {
+    for name in named_volumes(container) {
+        let mut inspect = new_command();
+        build_volume_inspect_command(&mut inspect, name);
+
+        let output = try_run_and_wait_for_command(&mut inspect)?;
+
+        if output.status.success() {
+            continue;
+        }
+
+        // both docker and podman report "no such volume"
+        if !String::from_utf8_lossy(&output.stderr)
+            .to_uppercase()
+            .contains("NO SUCH VOLUME")
+        {
+            return Err(command_error(&inspect, output));
+        }
+
+        let mut create = new_command();
+        build_volume_create_command(&mut create, name);
+        run_and_wait_for_command(&mut create)?;
+    }
+
+    Ok(())
+}
+
+///
+/// Makes sure the image of a container is present as demanded by its [PullPolicy].
+///
+pub fn ensure_image<C: Client>(client: &C, container: &Container) -> ContainerResult<()> {
+    let image = &container.image;
+
+    match container.pull_policy {
+        PullPolicy::Always => client.pull(image),
+        PullPolicy::IfNotPresent if client.image_exists(image)? => Ok(()),
+        PullPolicy::IfNotPresent => client.pull(image),
+        PullPolicy::Never if client.image_exists(image)? => Ok(()),
+        PullPolicy::Never => Err(ContainersError::ImageNotFound {
+            message: format!("{image} is not present and the pull policy is never"),
+        }),
+    }
+}
+
 #[instrument(skip_all)]
 pub fn inspect<C: Client>(
     client: &C,
@@ -154,131 +854,254 @@ pub fn inspect<C: Client>(
 
     let output = try_run_and_wait_for_command(&mut cmd)?;
 
-    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
-    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+    parse_inspect_output(&cmd, output)
+}
 
+pub fn parse_inspect_output(
+    command: &Command,
+    output: Output,
+) -> ContainerResult<Option<DetailedContainerInfo>> {
     match output.status.code() {
         Some(0) => {
-            let container_infos: Vec<DetailedContainerInfo> = serde_json::from_str(&stdout)?;
+            let container_infos: Vec<DetailedContainerInfo> =
+                serde_json::from_slice(&output.stdout)?;
 
             debug!(?container_infos, "Inspect container");
 
-            match container_infos.get(0) {
+            match container_infos.first() {
                 Some(info) => Ok(Some(info.to_owned())),
                 None => Ok(None),
             }
         }
         _ => {
-            if stderr.to_uppercase().contains("NO SUCH OBJECT") {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_uppercase();
+
+            if stderr.contains("NO SUCH OBJECT") {
                 Ok(None)
             } else {
-                Err(ContainersError::CommandError(output))
+                Err(command_error(command, output))
             }
         }
     }
 }
 
 #[instrument(skip_all)]
-pub fn do_log<C: Client>(client: &C, container: &Container) -> ContainerResult<Log> {
-    let mut cmd = client.command();
+pub fn network_exists(command: &mut Command, network: &Network) -> ContainerResult<bool> {
+    build_network_inspect_command(command, network);
 
-    build_log_command(&mut cmd, container);
+    let output = try_run_and_wait_for_command(command)?;
 
-    let (reader, writer) = os_pipe::pipe()?;
+    match output.status.code() {
+        Some(0) => Ok(true),
+        _ => {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_uppercase();
 
-    // redirect all process out to that single pipe
-    let cmd = cmd
-        .stdout(writer.try_clone().unwrap())
-        .stderr(writer)
-        .spawn()?;
+            // docker reports "no such network" or "not found", podman "network not found"
+            if stderr.contains("NO SUCH NETWORK") || stderr.contains("NOT FOUND") {
+                Ok(false)
+            } else {
+                Err(command_error(command, output))
+            }
+        }
+    }
+}
 
-    debug!(?cmd, "Reading log");
+///
+/// Removes the containers and networks of contain-rs processes that are gone, see [Client::prune_orphans].
+///
+#[instrument(skip_all)]
+pub fn prune_orphans(new_command: impl Fn() -> Command) -> ContainerResult<Pruned> {
+    let mut pruned = Pruned::default();
+    let filter = format!("label={PID_LABEL}");
 
-    Ok(Log { reader })
-}
+    let mut ps = new_command();
+    ps.args(["ps", "-a", "-q", "--no-trunc", "--filter", &filter]);
 
-pub fn wait_for<C: Client>(client: &C, container: &Container) -> ContainerResult<()> {
-    let result = match &container.wait_strategy {
-        Some(strategy) => match strategy {
-            WaitStrategy::LogMessage { pattern } => {
-                wait_for_log(client, container, strategy, pattern)
-            }
-            WaitStrategy::HealthCheck => Ok(wait_for_health_check(client, container)?),
-            WaitStrategy::WaitTime { duration } => Ok(wait_for_time(duration.to_owned())?),
-        },
-        None => Ok(()),
-    };
+    // containers are removed first, as networks can not be removed while in use
+    for id in run_and_wait_for_command(&mut ps)?.split_whitespace() {
+        let mut inspect = new_command();
+        inspect.arg("inspect").arg(id);
 
-    thread::sleep(container.additional_wait_period);
+        let output = try_run_and_wait_for_command(&mut inspect)?;
 
-    result
+        // another process might have removed the container in the meantime
+        let Some(container) = parse_inspect_output(&inspect, output)? else {
+            continue;
+        };
+
+        if is_orphan(container.labels()) {
+            let name = container.name.trim_start_matches('/');
+
+            info!(container = name, "Removing orphaned container");
+
+            let mut rm = new_command();
+            rm.arg("rm").arg("-f").arg(id);
+            run_and_wait_for_command(&mut rm)?;
+
+            pruned.containers.push(name.to_string());
+        }
+    }
+
+    let mut ls = new_command();
+    ls.args(["network", "ls", "-q", "--filter", &filter]);
+
+    for id in run_and_wait_for_command(&mut ls)?.split_whitespace() {
+        let mut inspect = new_command();
+        inspect.arg("network").arg("inspect").arg(id);
+
+        let output = try_run_and_wait_for_command(&mut inspect)?;
+
+        if !output.status.success() {
+            debug!(network = id, "Network disappeared while pruning");
+            continue;
+        }
+
+        let networks: Vec<NetworkInfo> = serde_json::from_slice(&output.stdout)?;
+
+        for network in networks.into_iter().filter(|n| is_orphan(&n.labels)) {
+            info!(network = network.name, "Removing orphaned network");
+
+            let mut rm = new_command();
+            rm.arg("network").arg("rm").arg(&network.name);
+            run_and_wait_for_command(&mut rm)?;
+
+            pruned.networks.push(network.name);
+        }
+    }
+
+    Ok(pruned)
 }
 
-fn wait_for_time(duration: Duration) -> ContainerResult<()> {
-    thread::sleep(duration);
-    Ok(())
+///
+/// Messages of the runtime itself failing to run a command, e.g. because the container is not running
+/// or the command can not be invoked.
+///
+const EXEC_RUNTIME_FAILURES: [&str; 3] = [
+    "Error response from daemon:",
+    "OCI runtime",
+    "container state improper",
+];
+
+///
+/// Runs a command in a container, a failure of the runtime is an error rather than the output of the command.
+///
+/// The exit codes of the runtime overlap with the ones of the command, so failures are told apart by their message.
+///
+#[instrument(skip_all)]
+pub fn exec(
+    command: &mut Command,
+    container: &Container,
+    exec: &Exec,
+) -> ContainerResult<ExecOutput> {
+    build_exec_command(command, container, exec);
+
+    let output = try_run_and_wait_for_command(command)?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let runtime_failed = !output.status.success()
+        && (EXEC_RUNTIME_FAILURES
+            .iter()
+            .any(|message| stderr.contains(message))
+            || daemon_error(stderr.trim()).is_some());
+
+    if runtime_failed {
+        return Err(command_error(command, output));
+    }
+
+    Ok(ExecOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        exit_code: output.status.code(),
+    })
 }
 
-fn wait_for_log<C: Client>(
-    client: &C,
+#[instrument(skip_all)]
+pub fn exec_stream(
+    command: &mut Command,
     container: &Container,
-    wait_strategy: &WaitStrategy,
-    pattern: &Regex,
-) -> ContainerResult<()> {
-    let log = do_log(client, container)?;
+    exec: &Exec,
+) -> ContainerResult<ExecStream> {
+    build_exec_command(command, container, exec);
+
+    debug!(?command, "Running command");
 
-    do_wait_for_log(pattern, container, wait_strategy, log)?;
+    let (reader, writer) = os_pipe::pipe()?;
 
-    Ok(())
+    let mut child = command
+        .stdout(writer.try_clone()?)
+        .stderr(writer)
+        .spawn()
+        .map_err(|e| spawn_error(command, e))?;
+
+    Ok(ExecStream::new(reader, move || Ok(child.wait()?.code())))
 }
 
+///
+/// Subscribes to container events, the cli keeps running until the returned [Events] are dropped.
+///
 #[instrument(skip_all)]
-fn do_wait_for_log(
-    pattern: &Regex,
+pub fn events(mut command: Command, filter: &EventFilter) -> ContainerResult<Events> {
+    build_events_command(&mut command, filter);
+
+    debug!(?command, "Running command");
+
+    let child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| spawn_error(&command, e))?;
+
+    Ok(Events::from_process(command, child))
+}
+
+#[instrument(skip_all)]
+pub fn do_log<C: Client>(client: &C, container: &Container) -> ContainerResult<Log> {
+    let mut cmd = client.command();
+
+    build_log_command(&mut cmd, container);
+
+    let (stdout, stdout_writer) = os_pipe::pipe()?;
+    let (stderr, stderr_writer) = os_pipe::pipe()?;
+
+    // the runtime writes the streams of the container to its own stdout and stderr
+    let child = cmd
+        .stdout(stdout_writer)
+        .stderr(stderr_writer)
+        .spawn()
+        .map_err(|e| spawn_error(&cmd, e))?;
+
+    debug!(?child, "Reading log");
+
+    Ok(Log::from_process(stdout, stderr, child)?)
+}
+
+///
+/// Waits for a container to be ready, as defined by its [WaitStrategy].
+///
+/// Gives up after the container's [startup timeout](Container::startup_timeout).
+///
+pub fn wait_for<C: Client + Send + 'static>(
+    client: &C,
     container: &Container,
-    wait_strategy: &WaitStrategy,
-    mut log: Log,
 ) -> ContainerResult<()> {
-    debug!(?pattern, "Searching log");
-    for result in log.stream().lines() {
-        let line = result?;
-        debug!(?pattern, ?line, "Searching for pattern");
-        if pattern.is_match(&line) {
-            debug!(?line, ?pattern, "Found pattern");
-            return Ok(());
-        }
-    }
+    let result = wait_for_strategy(client, container, &Cancel::default());
 
-    Err(ContainersError::ContainerWaitFailed {
-        container_name: container.name.clone(),
-        wait_strategy: wait_strategy.clone(),
-    })
+    thread::sleep(container.additional_wait_period);
+
+    result
 }
 
-#[instrument(skip_all)]
-fn wait_for_health_check<C: Client>(client: &C, container: &Container) -> ContainerResult<()> {
-    loop {
-        debug!("Checking health for {}", &container.name);
-
-        match inspect(client, container)? {
-            Some(info) => {
-                if let Some(health) = info.state.health {
-                    match health.status {
-                        ContainerStatus::Healthy => return Ok(()),
-                        ContainerStatus::Starting => thread::sleep(Duration::from_millis(200)),
-                        _ => {
-                            return Err(ContainersError::ContainerStatusError {
-                                status: health.status,
-                            })
-                        }
-                    }
-                }
-            }
-            None => {
-                return Err(ContainersError::ContainerNotExists {
-                    container_name: container.name.clone(),
-                })
-            }
-        }
+///
+/// Waits for the [WaitStrategy] of a container until it succeeds or waiting is cancelled,
+/// leaving out the [additional wait period](Container::additional_wait_period).
+///
+pub(crate) fn wait_for_strategy<C: Client + Send + 'static>(
+    client: &C,
+    container: &Container,
+    cancel: &Cancel,
+) -> ContainerResult<()> {
+    match &container.wait_strategy {
+        Some(strategy) => wait::wait(client, container, strategy, cancel),
+        None => Ok(()),
     }
 }
@@ -0,0 +1,1294 @@
+//!
+//! Evaluates [wait strategies](WaitStrategy) against a running container.
+//!
+//! Every strategy is turned into a [Probe], which is polled until it is ready.
+//! This allows composite strategies to evaluate their parts side by side on a single thread.
+//!
+
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    task::Poll,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use native_tls::TlsConnector;
+use regex::Regex;
+use tracing::*;
+
+use crate::{
+    container::{Container, Exec, HttpWait, LogStream, Port, WaitStrategy},
+    error::{ContainerResult, ContainersError},
+    rt::{ContainerStatus, DetailedContainerInfo, EventKind, ExecOutput},
+};
+
+use super::{
+    http::{exchange, Stream},
+    Client, EventFilter, Events, Log,
+};
+
+///
+/// How many log lines are reported, when waiting for a container timed out.
+///
+pub(crate) const LOG_TAIL_LINES: usize = 20;
+
+/// The interval in which the state of a container is polled.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How long connecting to a container and waiting for its response may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a connection has to stay open, to tell a listening container from a proxy of the runtime.
+const CLOSE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How long to wait for more lines, when reading the log that has not been followed.
+const LOG_READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How far back the events of a container that is removed automatically are replayed, to learn its exit code.
+const REMOVED_EVENTS_LOOKBACK: Duration = Duration::from_secs(60);
+
+/// The exit code reported for a container that is gone, without an event telling how it exited.
+const UNKNOWN_EXIT_CODE: i32 = -1;
+
+///
+/// Waits for the strategy to succeed, giving up after the container's [startup timeout](Container::startup_timeout).
+///
+/// A plain [wait time](WaitStrategy::WaitTime) is not bounded by the startup timeout, it is meant to take as long as it says.
+///
+pub(crate) fn wait<C: Client + Send + 'static>(
+    client: &C,
+    container: &Container,
+    strategy: &WaitStrategy,
+    cancel: &Cancel,
+) -> ContainerResult<()> {
+    let mut wait = Wait::new(client, container, strategy, cancel)?;
+
+    let mut probe = match strategy {
+        WaitStrategy::Timeout { .. } | WaitStrategy::WaitTime { .. } => Probe::new(strategy, &wait),
+        _ => Probe::timeout(strategy, container.startup_timeout, &wait),
+    };
+
+    loop {
+        if cancel.is_cancelled() {
+            debug!(container = container.name, "Waiting cancelled");
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "waiting was cancelled").into());
+        }
+
+        let poll = probe.poll(&mut wait);
+
+        if let Poll::Ready(Ok(())) = poll {
+            return Ok(());
+        }
+
+        // a container that is removed automatically is gone right after it stopped
+        let removed = container.auto_remove
+            && matches!(
+                poll,
+                Poll::Ready(Err(ContainersError::ContainerNotExists { .. }))
+            );
+
+        let exit_code = if removed {
+            Some(wait.removed_exit_code())
+        } else {
+            wait.exit_code()?
+        };
+
+        // a stopped container can not become ready anymore, unless the strategy waits for it to exit
+        if let Some(exit_code) = exit_code {
+            wait.stopped = true;
+
+            return match probe.poll(&mut wait) {
+                Poll::Ready(Ok(())) => Ok(()),
+                _ => Err(wait.exited_error(exit_code)),
+            };
+        }
+
+        if let Poll::Ready(result) = poll {
+            return result;
+        }
+
+        let next_poll = [probe.next_poll(&wait), wait.next_exit_check()]
+            .into_iter()
+            .flatten()
+            .min();
+
+        wait.await_updates(next_poll);
+    }
+}
+
+///
+/// Cancels waiting for a container from another thread.
+///
+#[derive(Clone, Default)]
+pub(crate) struct Cancel(Arc<CancelState>);
+
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    wake: Mutex<Option<mpsc::Sender<Update>>>,
+}
+
+impl Cancel {
+    #[cfg(feature = "async")]
+    fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+
+        if let Some(wake) = self.0.wake.lock().unwrap().as_ref() {
+            let _ = wake.send(Update::Cancelled);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    ///
+    /// Wakes a wait that sleeps until its next poll, once cancelled.
+    ///
+    fn wake(&self, sender: mpsc::Sender<Update>) {
+        *self.0.wake.lock().unwrap() = Some(sender);
+    }
+}
+
+///
+/// Cancels waiting once dropped, e.g. together with the future that awaits it.
+///
+#[cfg(feature = "async")]
+pub(crate) struct CancelOnDrop(pub(crate) Cancel);
+
+#[cfg(feature = "async")]
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+///
+/// The state of waiting for a single container.
+///
+/// When a strategy looks for log messages, the log of the container is followed in the background for the whole wait,
+/// so every line is seen and the end of the log tells that the container stopped.
+/// When a strategy checks the health, the events of the container report status changes and when it stopped.
+/// Otherwise the container is inspected regularly, to notice when it stopped.
+///
+/// The log and the events are kept, so reading them stops once waiting is done.
+///
+struct Wait<'a, C: Client> {
+    client: &'a C,
+    container: &'a Container,
+    updates: mpsc::Receiver<Update>,
+    sender: mpsc::Sender<Update>,
+    log: Option<Log>,
+    open_streams: usize,
+    lines: Vec<LogLine>,
+    _events: Option<Events>,
+    events_ended: bool,
+    health: Option<ContainerStatus>,
+    died: bool,
+    reported_exit_code: Option<i32>,
+    last_inspect: Option<Instant>,
+    stopped: bool,
+}
+
+impl<'a, C: Client + Send + 'static> Wait<'a, C> {
+    fn new(
+        client: &'a C,
+        container: &'a Container,
+        strategy: &WaitStrategy,
+        cancel: &Cancel,
+    ) -> ContainerResult<Self> {
+        let (sender, updates) = mpsc::channel();
+
+        cancel.wake(sender.clone());
+
+        let log = if contains(strategy, |s| {
+            matches!(s, WaitStrategy::LogMessage { .. } | WaitStrategy::Log(_))
+        }) {
+            match client.log(container)? {
+                Some(log) => {
+                    read_lines(&log, &sender)?;
+                    Some(log)
+                }
+                // the container stopped and got removed already, which the exit check tells
+                None if container.auto_remove => None,
+                None => {
+                    return Err(ContainersError::ContainerNotExists {
+                        container_name: container.name.clone(),
+                    })
+                }
+            }
+        } else {
+            None
+        };
+
+        // subscribing before the first check makes sure no status change is missed,
+        // the events are the only place to learn the exit code of a container that is removed automatically
+        let events = if container.auto_remove
+            || contains(strategy, |s| matches!(s, WaitStrategy::HealthCheck))
+        {
+            subscribe(client, container, &sender)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            client,
+            container,
+            updates,
+            sender,
+            open_streams: if log.is_some() { 2 } else { 0 },
+            log,
+            lines: Vec::new(),
+            events_ended: events.is_none(),
+            _events: events,
+            health: None,
+            died: false,
+            reported_exit_code: None,
+            last_inspect: None,
+            stopped: false,
+        })
+    }
+
+    fn log_ended(&self) -> bool {
+        self.log.is_some() && self.open_streams == 0
+    }
+
+    fn subscribed(&self) -> bool {
+        !self.events_ended
+    }
+
+    ///
+    /// When the container has to be inspected next, to notice that it stopped.
+    ///
+    /// A followed log only ends and events only report that the container died, once it stopped.
+    /// Otherwise the container is inspected every poll interval.
+    ///
+    fn next_exit_check(&self) -> Option<Instant> {
+        if self.died {
+            return Some(Instant::now());
+        }
+
+        let notified = match self.log {
+            Some(_) => !self.log_ended(),
+            // the first check covers a container that stopped before subscribing
+            None => self.subscribed() && self.last_inspect.is_some(),
+        };
+
+        if notified {
+            None
+        } else {
+            Some(
+                self.last_inspect
+                    .map_or_else(Instant::now, |last_inspect| last_inspect + POLL_INTERVAL),
+            )
+        }
+    }
+
+    ///
+    /// The exit code of the container, once it stopped.
+    ///
+    fn exit_code(&mut self) -> ContainerResult<Option<i32>> {
+        if self
+            .next_exit_check()
+            .is_none_or(|next_check| next_check > Instant::now())
+        {
+            return Ok(None);
+        }
+
+        self.last_inspect = Some(Instant::now());
+        self.died = false;
+
+        self.stopped_with()
+    }
+
+    ///
+    /// Inspects the container for its exit code, none while it is running.
+    ///
+    fn stopped_with(&mut self) -> ContainerResult<Option<i32>> {
+        match self.inspect() {
+            Ok(info) => Ok((!info.state.running).then_some(info.state.exit_code)),
+            Err(ContainersError::ContainerNotExists { .. }) if self.container.auto_remove => {
+                Ok(Some(self.removed_exit_code()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    ///
+    /// The exit code of a container that got removed automatically, as its events reported it.
+    ///
+    /// The event might still be on its way, when the container is found to be gone.
+    ///
+    fn removed_exit_code(&mut self) -> i32 {
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+
+        self.collect_updates();
+
+        while self.reported_exit_code.is_none() && self.subscribed() && Instant::now() < deadline {
+            self.await_updates(Some(deadline));
+        }
+
+        self.reported_exit_code.unwrap_or(UNKNOWN_EXIT_CODE)
+    }
+
+    ///
+    /// Blocks until an update arrives or the next poll is due, then collects all available updates.
+    ///
+    fn await_updates(&mut self, next_poll: Option<Instant>) {
+        let update = match next_poll {
+            Some(next_poll) => self
+                .updates
+                .recv_timeout(next_poll.saturating_duration_since(Instant::now())),
+            None => self
+                .updates
+                .recv()
+                .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+
+        match update {
+            Ok(update) => {
+                self.apply(update);
+                self.collect_updates();
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            // nothing is read in the background anymore
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                thread::sleep(next_poll.map_or(POLL_INTERVAL, |next_poll| {
+                    next_poll.saturating_duration_since(Instant::now())
+                }))
+            }
+        }
+    }
+
+    fn collect_updates(&mut self) {
+        while let Ok(update) = self.updates.try_recv() {
+            self.apply(update);
+        }
+    }
+
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Line(line) => self.lines.push(line),
+            Update::StreamEnded => self.open_streams = self.open_streams.saturating_sub(1),
+            Update::Event(EventKind::HealthStatus(status)) => {
+                debug!(?status, "Health status changed");
+                self.health = Some(status);
+            }
+            Update::Event(EventKind::Die { exit_code }) => {
+                self.died = true;
+                self.reported_exit_code = exit_code.or(self.reported_exit_code);
+            }
+            Update::Event(_) => {}
+            Update::ExecFinished => {}
+            #[cfg(feature = "async")]
+            Update::Cancelled => {}
+            Update::EventsEnded => {
+                debug!("Events ended, polling the container instead");
+                self.events_ended = true;
+            }
+        }
+    }
+
+    ///
+    /// Reads the log up to now, when it is not followed anyway.
+    ///
+    /// The log is followed for a moment, until no more lines arrive or the connect timeout passed.
+    ///
+    fn read_log(&mut self) {
+        if self.log.is_some() {
+            return self.collect_updates();
+        }
+
+        let (sender, updates) = mpsc::channel();
+
+        let log = match self.client.log(self.container) {
+            Ok(Some(log)) => log,
+            Ok(None) => return,
+            Err(e) => {
+                debug!(%e, "Failed to read the log");
+                return;
+            }
+        };
+
+        if let Err(e) = read_lines(&log, &sender) {
+            debug!(%e, "Failed to read the log");
+            return;
+        }
+
+        drop(sender);
+
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+
+        while let Ok(update) = updates.recv_timeout(LOG_READ_TIMEOUT) {
+            if let Update::Line(line) = update {
+                self.lines.push(line);
+            }
+
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+    }
+
+    fn last_log_lines(&self) -> Vec<String> {
+        self.lines[self.lines.len().saturating_sub(LOG_TAIL_LINES)..]
+            .iter()
+            .map(|line| line.text.clone())
+            .collect()
+    }
+
+    fn exited_error(&mut self, exit_code: i32) -> ContainersError {
+        self.read_log();
+
+        ContainersError::ContainerExited {
+            container_name: self.container.name.clone(),
+            exit_code,
+            last_log_lines: self.last_log_lines(),
+        }
+    }
+
+    fn timeout_error(&mut self, strategy: WaitStrategy, timeout: Duration) -> ContainersError {
+        self.read_log();
+
+        let last_state = match self.client.inspect(self.container) {
+            Ok(info) => info.map(|info| Box::new(info.state)),
+            Err(e) => {
+                debug!(%e, "Failed to inspect container after timeout");
+                None
+            }
+        };
+
+        ContainersError::WaitTimeout {
+            container_name: self.container.name.clone(),
+            wait_strategy: strategy,
+            timeout,
+            last_state,
+            last_log_lines: self.last_log_lines(),
+        }
+    }
+
+    ///
+    /// Runs a command in the container in the background, so the wait can still time out while the runtime does not answer.
+    ///
+    /// A command that is still running once waiting is done, is left to finish on its own.
+    ///
+    fn exec(&self, exec: &Exec) -> mpsc::Receiver<ContainerResult<ExecOutput>> {
+        let (sender, receiver) = mpsc::channel();
+        let updates = self.sender.clone();
+        let (client, container, exec) = (self.client.clone(), self.container.clone(), exec.clone());
+
+        thread::spawn(move || {
+            let _ = sender.send(client.exec(&container, &exec));
+            let _ = updates.send(Update::ExecFinished);
+        });
+
+        receiver
+    }
+
+    fn inspect(&self) -> ContainerResult<DetailedContainerInfo> {
+        self.client
+            .inspect(self.container)?
+            .ok_or_else(|| ContainersError::ContainerNotExists {
+                container_name: self.container.name.clone(),
+            })
+    }
+
+    ///
+    /// The host and port a container port is published on, none if it is not mapped yet.
+    ///
+    /// Ports bound to all interfaces are reached on the host of the runtime.
+    ///
+    fn published_address(&self, info: &DetailedContainerInfo, port: Port) -> Option<(String, u16)> {
+        let binding = info.port_binding(port)?;
+        let host_port = binding.host_port.parse().ok()?;
+
+        let host = match binding.host_ip.as_str() {
+            "" | "0.0.0.0" | "::" => self.client.host(),
+            host_ip => host_ip.to_string(),
+        };
+
+        Some((host, host_port))
+    }
+
+    fn wait_failed(&self, strategy: &WaitStrategy) -> ContainersError {
+        ContainersError::ContainerWaitFailed {
+            container_name: self.container.name.clone(),
+            wait_strategy: strategy.clone(),
+        }
+    }
+}
+
+///
+/// A strategy in the middle of being evaluated.
+///
+struct Probe<'s> {
+    strategy: &'s WaitStrategy,
+    state: State<'s>,
+}
+
+enum State<'s> {
+    LogMessage {
+        pattern: &'s Regex,
+        times: usize,
+        stream: Option<LogStream>,
+        next_line: usize,
+        matches: usize,
+    },
+    HealthCheck {
+        last_check: Option<Instant>,
+    },
+    WaitTime {
+        until: Instant,
+    },
+    Http {
+        http: &'s HttpWait,
+        last_poll: Option<Instant>,
+    },
+    PortOpen {
+        ports: Vec<Port>,
+        last_check: Option<Instant>,
+    },
+    Command {
+        exec: &'s Exec,
+        last_run: Option<Instant>,
+        running: Option<mpsc::Receiver<ContainerResult<ExecOutput>>>,
+    },
+    Exit {
+        expected_code: i32,
+        last_check: Option<Instant>,
+    },
+    Timeout {
+        probe: Box<Probe<'s>>,
+        timeout: Duration,
+        deadline: Instant,
+    },
+    All {
+        probes: Vec<Probe<'s>>,
+        consumed_lines: usize,
+    },
+    Any {
+        probes: Vec<Probe<'s>>,
+        error: Option<ContainersError>,
+        consumed_lines: usize,
+    },
+    Sequence {
+        probe: Option<Box<Probe<'s>>>,
+        next: std::slice::Iter<'s, WaitStrategy>,
+        consumed_lines: usize,
+    },
+}
+
+impl<'s> Probe<'s> {
+    fn new<C: Client + Send + 'static>(strategy: &'s WaitStrategy, wait: &Wait<C>) -> Self {
+        let state = match strategy {
+            WaitStrategy::LogMessage { pattern } => State::LogMessage {
+                pattern,
+                times: 1,
+                stream: None,
+                next_line: 0,
+                matches: 0,
+            },
+            WaitStrategy::Log(log) => State::LogMessage {
+                pattern: &log.pattern,
+                times: log.times,
+                stream: log.stream,
+                next_line: 0,
+                matches: 0,
+            },
+            WaitStrategy::HealthCheck => State::HealthCheck { last_check: None },
+            WaitStrategy::WaitTime { duration } => State::WaitTime {
+                until: Instant::now() + *duration,
+            },
+            WaitStrategy::Http(http) => State::Http {
+                http,
+                last_poll: None,
+            },
+            WaitStrategy::PortOpen { ports } if ports.is_empty() => State::PortOpen {
+                ports: wait
+                    .container
+                    .port_mappings
+                    .iter()
+                    .map(|mapping| mapping.target.clone())
+                    .chain(wait.container.exposed_ports.iter().cloned())
+                    .filter(|port| !port.with_protocol().ends_with("/udp"))
+                    .collect(),
+                last_check: None,
+            },
+            WaitStrategy::PortOpen { ports } => State::PortOpen {
+                ports: ports.clone(),
+                last_check: None,
+            },
+            WaitStrategy::Command { exec } => State::Command {
+                exec,
+                last_run: None,
+                running: None,
+            },
+            WaitStrategy::Exit { expected_code } => State::Exit {
+                expected_code: *expected_code,
+                last_check: None,
+            },
+            WaitStrategy::Timeout { strategy, timeout } => {
+                return Probe::timeout(strategy, *timeout, wait)
+            }
+            WaitStrategy::All { strategies } => State::All {
+                probes: strategies.iter().map(|s| Probe::new(s, wait)).collect(),
+                consumed_lines: 0,
+            },
+            WaitStrategy::Any { strategies } => State::Any {
+                probes: strategies.iter().map(|s| Probe::new(s, wait)).collect(),
+                error: None,
+                consumed_lines: 0,
+            },
+            WaitStrategy::Sequence { strategies } => {
+                let mut next = strategies.iter();
+
+                State::Sequence {
+                    probe: next.next().map(|first| Box::new(Probe::new(first, wait))),
+                    next,
+                    consumed_lines: 0,
+                }
+            }
+        };
+
+        Self { strategy, state }
+    }
+
+    fn timeout<C: Client + Send + 'static>(
+        strategy: &'s WaitStrategy,
+        timeout: Duration,
+        wait: &Wait<C>,
+    ) -> Self {
+        Self {
+            strategy,
+            state: State::Timeout {
+                probe: Box::new(Probe::new(strategy, wait)),
+                timeout,
+                deadline: Instant::now() + timeout,
+            },
+        }
+    }
+
+    ///
+    /// Starts a probe that only considers the log lines after the first `lines`.
+    ///
+    fn starting_at<C: Client + Send + 'static>(
+        strategy: &'s WaitStrategy,
+        lines: usize,
+        wait: &Wait<C>,
+    ) -> Self {
+        let mut probe = Probe::new(strategy, wait);
+        probe.skip_lines(lines);
+        probe
+    }
+
+    fn skip_lines(&mut self, lines: usize) {
+        match &mut self.state {
+            State::LogMessage { next_line, .. } => *next_line = lines,
+            State::Timeout { probe, .. } => probe.skip_lines(lines),
+            State::Sequence {
+                probe: Some(probe), ..
+            } => probe.skip_lines(lines),
+            State::All { probes, .. } | State::Any { probes, .. } => {
+                probes.iter_mut().for_each(|probe| probe.skip_lines(lines))
+            }
+            State::HealthCheck { .. }
+            | State::WaitTime { .. }
+            | State::Http { .. }
+            | State::PortOpen { .. }
+            | State::Command { .. }
+            | State::Exit { .. }
+            | State::Sequence { .. } => {}
+        }
+    }
+
+    fn poll<C: Client + Send + 'static>(
+        &mut self,
+        wait: &mut Wait<C>,
+    ) -> Poll<ContainerResult<()>> {
+        let strategy = self.strategy;
+
+        match &mut self.state {
+            State::LogMessage {
+                pattern,
+                times,
+                stream,
+                next_line,
+                matches,
+            } => {
+                // reading stops at the line that satisfies the probe, the following ones are left to the next strategy of a sequence
+                while *matches < *times {
+                    let Some(line) = wait.lines.get(*next_line) else {
+                        break;
+                    };
+
+                    *next_line += 1;
+
+                    if stream.is_none_or(|stream| stream == line.stream)
+                        && pattern.is_match(&line.text)
+                    {
+                        *matches += 1;
+                        debug!(line = line.text, ?pattern, matches, "Found pattern");
+                    }
+                }
+
+                if *matches >= *times {
+                    Poll::Ready(Ok(()))
+                } else if wait.log_ended() {
+                    Poll::Ready(Err(wait.wait_failed(strategy)))
+                } else {
+                    Poll::Pending
+                }
+            }
+            State::HealthCheck { last_check } => {
+                if last_check.is_some() {
+                    // after the first check, status changes are reported by events
+                    match wait.health.clone() {
+                        Some(ContainerStatus::Starting) | None if wait.subscribed() => {
+                            return Poll::Pending
+                        }
+                        Some(ContainerStatus::Starting) | None => {}
+                        status => return health_poll(status),
+                    }
+                }
+
+                if last_check.is_some_and(|last_check| last_check.elapsed() < POLL_INTERVAL) {
+                    return Poll::Pending;
+                }
+
+                *last_check = Some(Instant::now());
+
+                debug!("Checking health for {}", &wait.container.name);
+
+                match wait.inspect() {
+                    Ok(info) => health_poll(info.state.health.map(|health| health.status)),
+                    Err(e) => Poll::Ready(Err(e)),
+                }
+            }
+            State::WaitTime { until } => {
+                if Instant::now() >= *until {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            }
+            State::Http { http, last_poll } => {
+                if last_poll.is_some_and(|last_poll| last_poll.elapsed() < http.poll_interval) {
+                    return Poll::Pending;
+                }
+
+                *last_poll = Some(Instant::now());
+
+                let address = match wait.inspect() {
+                    Ok(info) => wait.published_address(&info, http.port.clone()),
+                    Err(e) => return Poll::Ready(Err(e)),
+                };
+
+                match address.map(|(host, port)| request(http, &host, port)) {
+                    Some(Ok(())) => Poll::Ready(Ok(())),
+                    Some(Err(e)) => {
+                        debug!(%e, path = http.path, "Container not ready yet");
+                        Poll::Pending
+                    }
+                    None => {
+                        debug!(port = http.port.number, "Port not mapped yet");
+                        Poll::Pending
+                    }
+                }
+            }
+            State::PortOpen { ports, last_check } => {
+                if ports.is_empty() && last_check.is_none() {
+                    debug!("No ports to wait for");
+                    return Poll::Ready(Err(wait.wait_failed(strategy)));
+                }
+
+                if last_check.is_some_and(|last_check| last_check.elapsed() < POLL_INTERVAL) {
+                    return Poll::Pending;
+                }
+
+                *last_check = Some(Instant::now());
+
+                let info = match wait.inspect() {
+                    Ok(info) => info,
+                    Err(e) => return Poll::Ready(Err(e)),
+                };
+
+                ports.retain(|port| match wait.published_address(&info, port.clone()) {
+                    Some((host, host_port)) => !accepts_connections(&host, host_port),
+                    None => {
+                        debug!(port = port.number, "Port not mapped yet");
+                        true
+                    }
+                });
+
+                if ports.is_empty() {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            }
+            State::Command {
+                exec,
+                last_run,
+                running,
+            } => {
+                let output = match running.as_ref().map(mpsc::Receiver::try_recv) {
+                    Some(Err(mpsc::TryRecvError::Empty)) => return Poll::Pending,
+                    Some(output) => {
+                        *running = None;
+                        output
+                    }
+                    None => {
+                        if last_run.is_none_or(|last_run| last_run.elapsed() >= POLL_INTERVAL) {
+                            *last_run = Some(Instant::now());
+                            *running = Some(wait.exec(exec));
+                        }
+
+                        return Poll::Pending;
+                    }
+                };
+
+                match output {
+                    Ok(Ok(output)) if output.exit_code == Some(0) => Poll::Ready(Ok(())),
+                    Ok(Ok(output)) => {
+                        debug!(command = ?exec.command, exit_code = ?output.exit_code, stderr = output.stderr.trim(), "Command not successful yet");
+                        Poll::Pending
+                    }
+                    Ok(Err(e)) => {
+                        debug!(command = ?exec.command, %e, "Failed to run command");
+                        Poll::Pending
+                    }
+                    Err(_) => {
+                        debug!(command = ?exec.command, "Running the command panicked");
+                        Poll::Pending
+                    }
+                }
+            }
+            State::Exit {
+                expected_code,
+                last_check,
+            } => {
+                // once the container stopped, its exit code has to be checked right away
+                if !wait.stopped
+                    && last_check.is_some_and(|last_check| last_check.elapsed() < POLL_INTERVAL)
+                {
+                    return Poll::Pending;
+                }
+
+                *last_check = Some(Instant::now());
+
+                match wait.stopped_with() {
+                    Ok(None) => Poll::Pending,
+                    Ok(Some(exit_code)) if exit_code == *expected_code => Poll::Ready(Ok(())),
+                    Ok(Some(exit_code)) => Poll::Ready(Err(wait.exited_error(exit_code))),
+                    Err(e) => Poll::Ready(Err(e)),
+                }
+            }
+            State::Timeout {
+                probe,
+                timeout,
+                deadline,
+            } => match probe.poll(wait) {
+                Poll::Pending if Instant::now() >= *deadline => {
+                    Poll::Ready(Err(wait.timeout_error(probe.pending_strategy(), *timeout)))
+                }
+                poll => poll,
+            },
+            State::All {
+                probes,
+                consumed_lines,
+            } => {
+                let mut index = 0;
+
+                while index < probes.len() {
+                    match probes[index].poll(wait) {
+                        Poll::Ready(Ok(())) => {
+                            let probe = probes.remove(index);
+                            *consumed_lines = (*consumed_lines).max(probe.consumed_lines(wait));
+                        }
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(probes[index].failed(e))),
+                        Poll::Pending => index += 1,
+                    }
+                }
+
+                if probes.is_empty() {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            }
+            State::Any {
+                probes,
+                error,
+                consumed_lines,
+            } => {
+                let mut index = 0;
+
+                while index < probes.len() {
+                    match probes[index].poll(wait) {
+                        Poll::Ready(Ok(())) => {
+                            *consumed_lines = probes[index].consumed_lines(wait);
+                            return Poll::Ready(Ok(()));
+                        }
+                        Poll::Ready(Err(e)) => {
+                            debug!(%e, "Strategy failed, waiting for the remaining ones");
+                            *error = Some(probes.remove(index).failed(e));
+                        }
+                        Poll::Pending => index += 1,
+                    }
+                }
+
+                if probes.is_empty() {
+                    Poll::Ready(Err(error
+                        .take()
+                        .unwrap_or_else(|| wait.wait_failed(strategy))))
+                } else {
+                    Poll::Pending
+                }
+            }
+            State::Sequence {
+                probe,
+                next,
+                consumed_lines,
+            } => loop {
+                let Some(current) = probe else {
+                    return Poll::Ready(Ok(()));
+                };
+
+                match current.poll(wait) {
+                    Poll::Ready(Ok(())) => {
+                        *consumed_lines = current.consumed_lines(wait);
+                        *probe = next.next().map(|strategy| {
+                            Box::new(Probe::starting_at(strategy, *consumed_lines, wait))
+                        });
+                    }
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(current.failed(e))),
+                    Poll::Pending => return Poll::Pending,
+                }
+            },
+        }
+    }
+
+    ///
+    /// When the probe has to be polled again, none if it is only waiting for updates of the log or events.
+    ///
+    fn next_poll<C: Client + Send + 'static>(&self, wait: &Wait<C>) -> Option<Instant> {
+        let after = |last: &Option<Instant>, interval| {
+            Some(last.map_or_else(Instant::now, |last| last + interval))
+        };
+
+        match &self.state {
+            State::LogMessage { .. } => None,
+            State::HealthCheck { last_check } if last_check.is_some() && wait.subscribed() => None,
+            State::HealthCheck { last_check } => after(last_check, POLL_INTERVAL),
+            State::WaitTime { until } => Some(*until),
+            State::Http { http, last_poll } => after(last_poll, http.poll_interval),
+            State::PortOpen { last_check, .. } | State::Exit { last_check, .. } => {
+                after(last_check, POLL_INTERVAL)
+            }
+            State::Command {
+                running: Some(_), ..
+            } => None,
+            State::Command { last_run, .. } => after(last_run, POLL_INTERVAL),
+            State::Timeout {
+                probe, deadline, ..
+            } => probe
+                .next_poll(wait)
+                .map_or(Some(*deadline), |next_poll| Some(next_poll.min(*deadline))),
+            State::All { probes, .. } | State::Any { probes, .. } => probes
+                .iter()
+                .filter_map(|probe| probe.next_poll(wait))
+                .min(),
+            State::Sequence {
+                probe: Some(probe), ..
+            } => probe.next_poll(wait),
+            State::Sequence { probe: None, .. } => Some(Instant::now()),
+        }
+    }
+
+    ///
+    /// The strategy that is still being waited for, used to report timeouts.
+    ///
+    /// Of a composite strategy, only the parts that are not satisfied yet are reported.
+    ///
+    fn pending_strategy(&self) -> WaitStrategy {
+        let pending = |probes: &[Probe]| probes.iter().map(Probe::pending_strategy).collect();
+
+        match &self.state {
+            State::Timeout { probe, .. }
+            | State::Sequence {
+                probe: Some(probe), ..
+            } => probe.pending_strategy(),
+            State::All { probes, .. } | State::Any { probes, .. } if probes.len() == 1 => {
+                probes[0].pending_strategy()
+            }
+            State::All { probes, .. } if !probes.is_empty() => WaitStrategy::All {
+                strategies: pending(probes),
+            },
+            State::Any { probes, .. } if !probes.is_empty() => WaitStrategy::Any {
+                strategies: pending(probes),
+            },
+            _ => self.strategy.clone(),
+        }
+    }
+
+    ///
+    /// How many log lines the probe read up to the one that satisfied it.
+    ///
+    /// The next strategy of a sequence starts with the line after it.
+    ///
+    fn consumed_lines<C: Client + Send + 'static>(&self, wait: &Wait<C>) -> usize {
+        match &self.state {
+            State::LogMessage { next_line, .. } => *next_line,
+            State::Timeout { probe, .. } => probe.consumed_lines(wait),
+            State::All { consumed_lines, .. }
+            | State::Any { consumed_lines, .. }
+            | State::Sequence { consumed_lines, .. } => *consumed_lines,
+            State::HealthCheck { .. }
+            | State::WaitTime { .. }
+            | State::Http { .. }
+            | State::PortOpen { .. }
+            | State::Command { .. }
+            | State::Exit { .. } => wait.lines.len(),
+        }
+    }
+
+    ///
+    /// Tells which part of a composite strategy failed, unless the error already names a strategy.
+    ///
+    fn failed(&self, error: ContainersError) -> ContainersError {
+        match error {
+            ContainersError::ContainerWaitFailed { .. }
+            | ContainersError::WaitTimeout { .. }
+            | ContainersError::WaitStrategyFailed { .. } => error,
+            error => ContainersError::WaitStrategyFailed {
+                wait_strategy: self.strategy.clone(),
+                source: Box::new(error),
+            },
+        }
+    }
+}
+
+fn health_poll(status: Option<ContainerStatus>) -> Poll<ContainerResult<()>> {
+    match status {
+        Some(ContainerStatus::Healthy) => Poll::Ready(Ok(())),
+        Some(ContainerStatus::Starting) | None => Poll::Pending,
+        Some(status) => Poll::Ready(Err(ContainersError::ContainerStatusError { status })),
+    }
+}
+
+///
+/// Whether the host port accepts a connection and keeps it open.
+///
+/// A proxy of the runtime publishing the port may accept connections before the container listens,
+/// but closes them right away then.
+///
+fn accepts_connections(host: &str, host_port: u16) -> bool {
+    let result = connect(host, host_port).and_then(|stream| {
+        stream.set_read_timeout(Some(CLOSE_TIMEOUT))?;
+        (&stream).read(&mut [0])
+    });
+
+    match result {
+        Ok(0) => {
+            debug!(host, host_port, "Connection closed right away");
+            false
+        }
+        Ok(_) => true,
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            true
+        }
+        Err(e) => {
+            debug!(%e, host, host_port, "Port not open yet");
+            false
+        }
+    }
+}
+
+///
+/// Connects to a published port, trying every address the host resolves to.
+///
+fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut error = io::Error::new(io::ErrorKind::NotFound, format!("{host} did not resolve"));
+
+    for address in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e,
+        }
+    }
+
+    Err(error)
+}
+
+///
+/// Sends the request of a http strategy to a published port,
+/// failing when the container does not answer with the expected response.
+///
+fn request(http: &HttpWait, host: &str, host_port: u16) -> ContainerResult<()> {
+    let stream = connect(host, host_port)?;
+
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+
+    let host_header = if host.contains(':') {
+        format!("[{host}]:{host_port}")
+    } else {
+        format!("{host}:{host_port}")
+    };
+
+    let stream: Box<dyn Stream> = if http.tls {
+        let connector = TlsConnector::builder()
+            .danger_accept_invalid_certs(http.insecure)
+            .danger_accept_invalid_hostnames(http.insecure)
+            .build()
+            .map_err(io::Error::other)?;
+
+        Box::new(connector.connect(host, stream).map_err(io::Error::other)?)
+    } else {
+        Box::new(stream)
+    };
+
+    let response = exchange(stream, &host_header, &http.method, &http.path, None)?;
+    let status = response.status;
+    // responses to HEAD requests announce a length, but never send a body
+    let body = if http.method.eq_ignore_ascii_case("HEAD") {
+        String::new()
+    } else {
+        response.text()?
+    };
+
+    let body_matches = http
+        .body
+        .as_ref()
+        .is_none_or(|pattern| pattern.is_match(&body));
+
+    if http.accepts_status(status) && body_matches {
+        debug!(status, path = http.path, "Got expected response");
+        Ok(())
+    } else {
+        Err(ContainersError::ApiError {
+            status,
+            message: body.trim().to_string(),
+        })
+    }
+}
+
+struct LogLine {
+    stream: LogStream,
+    text: String,
+}
+
+///
+/// What is read about the container in the background while waiting.
+///
+enum Update {
+    Line(LogLine),
+    /// One of the two streams of the log ended.
+    StreamEnded,
+    Event(EventKind),
+    EventsEnded,
+    /// A command run by a probe finished.
+    ExecFinished,
+    #[cfg(feature = "async")]
+    Cancelled,
+}
+
+///
+/// Whether the strategy or any of the strategies it is composed of matches.
+///
+fn contains(strategy: &WaitStrategy, matches: fn(&WaitStrategy) -> bool) -> bool {
+    matches(strategy)
+        || match strategy {
+            WaitStrategy::Timeout { strategy, .. } => contains(strategy, matches),
+            WaitStrategy::All { strategies }
+            | WaitStrategy::Any { strategies }
+            | WaitStrategy::Sequence { strategies } => strategies
+                .iter()
+                .any(|strategy| contains(strategy, matches)),
+            _ => false,
+        }
+}
+
+///
+/// Reads the lines of both streams of a log in the background, until the log ends.
+///
+fn read_lines(log: &Log, sender: &mpsc::Sender<Update>) -> io::Result<()> {
+    for (stream, reader) in [
+        (LogStream::Stdout, log.stdout.try_clone()?),
+        (LogStream::Stderr, log.stderr.try_clone()?),
+    ] {
+        let sender = sender.clone();
+
+        thread::spawn(move || {
+            for text in BufReader::new(reader).lines().map_while(Result::ok) {
+                if sender.send(Update::Line(LogLine { stream, text })).is_err() {
+                    return;
+                }
+            }
+
+            let _ = sender.send(Update::StreamEnded);
+        });
+    }
+
+    Ok(())
+}
+
+///
+/// Subscribes to the events of the container from now on and reads them in the background,
+/// none if the runtime can not report them.
+///
+fn subscribe<C: Client + Send + 'static>(
+    client: &C,
+    container: &Container,
+    sender: &mpsc::Sender<Update>,
+) -> Option<Events> {
+    // a container that is removed automatically may have stopped already, its events are replayed for the exit code
+    let since = if container.auto_remove {
+        SystemTime::now() - REMOVED_EVENTS_LOOKBACK
+    } else {
+        SystemTime::now()
+    };
+
+    let filter = EventFilter::new().container(&container.name).since(since);
+
+    let mut events = match client.events(&filter) {
+        Ok(events) => events,
+        Err(e) => {
+            debug!(%e, "Failed to subscribe to events, polling the container instead");
+            return None;
+        }
+    };
+
+    let taken = events.take_stream();
+    let sender = sender.clone();
+
+    thread::spawn(move || {
+        for event in taken {
+            match event {
+                Ok(event) => {
+                    if sender.send(Update::Event(event.kind)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    debug!(%e, "Failed reading events");
+                    break;
+                }
+            }
+        }
+
+        let _ = sender.send(Update::EventsEnded);
+    });
+
+    Some(events)
+}
@@ -6,7 +6,7 @@
 //! See [Container] for further information on containers.
 //!
 
-use std::{fmt::Display, str::FromStr, time::Duration};
+use std::{fmt::Display, path::PathBuf, str::FromStr, time::Duration};
 
 use lazy_static::lazy_static;
 use rand::{distributions::Alphanumeric, Rng};
@@ -79,6 +79,73 @@ impl HealthCheck {
     }
 }
 
+///
+/// A command to be executed inside a running container.
+///
+/// ```
+/// use contain_rs_core::container::Exec;
+///
+/// let exec = Exec::new(["psql", "-c", "select 1"])
+///     .env_var("PGPASSWORD", "secret")
+///     .workdir("/tmp")
+///     .user("postgres");
+///
+/// assert_eq!(exec.command, vec!["psql", "-c", "select 1"]);
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct Exec {
+    pub command: Vec<String>,
+    pub env_vars: Vec<EnvVar>,
+    pub workdir: Option<String>,
+    pub user: Option<String>,
+}
+
+impl Exec {
+    pub fn new<I, S>(command: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            command: command.into_iter().map(Into::into).collect(),
+            env_vars: Vec::new(),
+            workdir: None,
+            user: None,
+        }
+    }
+
+    pub fn env_var<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.env_vars.push((key, value).into());
+        self
+    }
+
+    ///
+    /// The working directory inside the container to execute the command in.
+    ///
+    pub fn workdir(mut self, workdir: &str) -> Self {
+        self.workdir = Some(workdir.into());
+        self
+    }
+
+    ///
+    /// The user to execute the command as, either a name or a uid.
+    ///
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+}
+
+///
+/// An output stream of a container.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
 ///
 /// A wait strategy can be used to wait for a cotnainer to be ready.
 ///
@@ -87,27 +154,470 @@ pub enum WaitStrategy {
     ///
     /// Waits for a log message to appear.
     ///
+    /// The log is read from the start of the container,
+    /// so messages that were printed before waiting began count as well, each of them exactly once.
+    ///
     LogMessage { pattern: Regex },
     ///
+    /// Waits for a log message to appear a number of times, on one or both streams of the container.
+    ///
+    Log(LogWait),
+    ///
     /// Waits for the container to be healty.
     ///
     HealthCheck,
     ///
     /// Wait for some amount of time.
     ///
+    /// On its own it is not bounded by the [startup timeout](Container::startup_timeout),
+    /// nested in a composite strategy it is.
+    ///
     WaitTime { duration: Duration },
+    ///
+    /// Overrides the [startup timeout](Container::startup_timeout) for the wrapped strategy.
+    ///
+    /// Nested in a composite strategy, the wait is still bounded by any enclosing timeout.
+    ///
+    Timeout {
+        strategy: Box<WaitStrategy>,
+        timeout: Duration,
+    },
+    ///
+    /// Waits for all strategies to succeed, in any order.
+    ///
+    All { strategies: Vec<WaitStrategy> },
+    ///
+    /// Waits for the first of the strategies to succeed.
+    ///
+    Any { strategies: Vec<WaitStrategy> },
+    ///
+    /// Waits for the strategies one after the other.
+    ///
+    /// Log messages only count, when they appear after the previous strategy succeeded.
+    ///
+    Sequence { strategies: Vec<WaitStrategy> },
+    ///
+    /// Polls an http endpoint of the container from the host, through the port it is mapped to.
+    ///
+    /// This works for images that do not ship a tool like curl to run a [HealthCheck] with.
+    ///
+    Http(Box<HttpWait>),
+    ///
+    /// Waits until the host ports, the container ports are mapped to, accept tcp connections.
+    ///
+    /// Without any ports, every tcp port in [Container::port_mappings] and [Container::exposed_ports] is checked.
+    /// A proxy of the runtime may accept connections before the container itself listens,
+    /// connections it closes right away do not count.
+    ///
+    PortOpen { ports: Vec<Port> },
+    ///
+    /// Runs a command inside the container until it exits with `0`, e.g. `pg_isready`.
+    ///
+    /// Unlike a [HealthCheck], this does not rely on the healthcheck support of the runtime.
+    ///
+    Command { exec: Box<Exec> },
+    ///
+    /// Waits for the container to finish with the expected exit code, e.g. for one-shot migration runners.
+    ///
+    Exit { expected_code: i32 },
 }
 
-#[derive(Clone)]
+impl WaitStrategy {
+    ///
+    /// Wait for the pattern to appear once, on any stream.
+    ///
+    pub fn log_message(pattern: Regex) -> Self {
+        WaitStrategy::LogMessage { pattern }
+    }
+
+    ///
+    /// Wait for all of the strategies to succeed.
+    ///
+    /// ```
+    /// use contain_rs_core::container::WaitStrategy;
+    /// use regex::Regex;
+    ///
+    /// let strategy = WaitStrategy::all([
+    ///     WaitStrategy::HealthCheck,
+    ///     WaitStrategy::log_message(Regex::new("migrations complete").unwrap()),
+    /// ]);
+    ///
+    /// assert!(matches!(strategy, WaitStrategy::All { strategies } if strategies.len() == 2));
+    /// ```
+    ///
+    pub fn all(strategies: impl IntoIterator<Item = WaitStrategy>) -> Self {
+        WaitStrategy::All {
+            strategies: strategies.into_iter().collect(),
+        }
+    }
+
+    ///
+    /// Wait for any of the strategies to succeed.
+    ///
+    pub fn any(strategies: impl IntoIterator<Item = WaitStrategy>) -> Self {
+        WaitStrategy::Any {
+            strategies: strategies.into_iter().collect(),
+        }
+    }
+
+    ///
+    /// Wait for the strategies in order.
+    ///
+    pub fn sequence(strategies: impl IntoIterator<Item = WaitStrategy>) -> Self {
+        WaitStrategy::Sequence {
+            strategies: strategies.into_iter().collect(),
+        }
+    }
+
+    ///
+    /// Wait for the host port, the container port is mapped to, to accept connections.
+    ///
+    /// ```
+    /// use contain_rs_core::container::WaitStrategy;
+    ///
+    /// let strategy = WaitStrategy::port_open(6379);
+    ///
+    /// assert!(matches!(strategy, WaitStrategy::PortOpen { ports } if ports.len() == 1));
+    /// ```
+    ///
+    pub fn port_open(port: impl Into<Port>) -> Self {
+        WaitStrategy::PortOpen {
+            ports: vec![port.into()],
+        }
+    }
+
+    ///
+    /// Wait for all mapped ports of the container to accept connections.
+    ///
+    pub fn all_ports_open() -> Self {
+        WaitStrategy::PortOpen { ports: Vec::new() }
+    }
+
+    ///
+    /// Wait for a command run inside the container to succeed.
+    ///
+    /// ```
+    /// use contain_rs_core::container::WaitStrategy;
+    ///
+    /// let strategy = WaitStrategy::command(["redis-cli", "ping"]);
+    ///
+    /// assert!(matches!(strategy, WaitStrategy::Command { exec } if exec.command[0] == "redis-cli"));
+    /// ```
+    ///
+    pub fn command<I, S>(command: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        WaitStrategy::Command {
+            exec: Box::new(Exec::new(command)),
+        }
+    }
+
+    ///
+    /// Fail waiting with this strategy after `timeout`, instead of the container's startup timeout.
+    ///
+    /// ```
+    /// use contain_rs_core::container::WaitStrategy;
+    /// use std::time::Duration;
+    ///
+    /// let strategy = WaitStrategy::HealthCheck.with_timeout(Duration::from_secs(120));
+    ///
+    /// assert!(matches!(strategy, WaitStrategy::Timeout { .. }));
+    /// ```
+    ///
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        WaitStrategy::Timeout {
+            strategy: Box::new(self),
+            timeout,
+        }
+    }
+}
+
+///
+/// A log message that has to appear a number of times, like a [WaitStrategy::LogMessage].
+///
+/// ```
+/// use contain_rs_core::container::{LogStream, LogWait, WaitStrategy};
+/// use regex::Regex;
+///
+/// let strategy: WaitStrategy = LogWait::new(Regex::new("ready to accept connections").unwrap())
+///     .times(2)
+///     .stream(LogStream::Stderr)
+///     .into();
+///
+/// assert!(matches!(strategy, WaitStrategy::Log(log) if log.times == 2));
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct LogWait {
+    pub pattern: Regex,
+    pub times: usize,
+    ///
+    /// The stream the message has to appear on, any of them if none.
+    ///
+    pub stream: Option<LogStream>,
+}
+
+impl LogWait {
+    pub fn new(pattern: Regex) -> Self {
+        Self {
+            pattern,
+            times: 1,
+            stream: None,
+        }
+    }
+
+    ///
+    /// How many lines have to match the pattern.
+    ///
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = times;
+        self
+    }
+
+    pub fn stream(mut self, stream: LogStream) -> Self {
+        self.stream = Some(stream);
+        self
+    }
+}
+
+impl From<LogWait> for WaitStrategy {
+    fn from(log: LogWait) -> Self {
+        WaitStrategy::Log(log)
+    }
+}
+
+///
+/// An http request that is sent to a container until it gets the expected response.
+///
+/// Without any expected status codes, every `2xx` status counts as ready.
+///
+/// ```
+/// use contain_rs_core::container::{HttpWait, WaitStrategy};
+/// use regex::Regex;
+///
+/// let strategy: WaitStrategy = HttpWait::new(8080)
+///     .path("/health")
+///     .status(200)
+///     .body(Regex::new("UP").unwrap())
+///     .into();
+///
+/// assert!(matches!(strategy, WaitStrategy::Http(_)));
+/// ```
+///
+#[derive(Clone, Debug)]
+pub struct HttpWait {
+    pub port: Port,
+    pub path: String,
+    pub method: String,
+    pub status_codes: Vec<u16>,
+    pub body: Option<Regex>,
+    pub tls: bool,
+    pub insecure: bool,
+    pub poll_interval: Duration,
+}
+
+impl HttpWait {
+    pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+    pub fn new(port: impl Into<Port>) -> Self {
+        Self {
+            port: port.into(),
+            path: "/".into(),
+            method: "GET".into(),
+            status_codes: Vec::new(),
+            body: None,
+            tls: false,
+            insecure: false,
+            poll_interval: Self::DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn method(mut self, method: &str) -> Self {
+        self.method = method.into();
+        self
+    }
+
+    ///
+    /// Adds a status code that counts as ready.
+    ///
+    pub fn status(mut self, status: u16) -> Self {
+        self.status_codes.push(status);
+        self
+    }
+
+    ///
+    /// Only counts responses as ready, whose body matches the pattern.
+    ///
+    pub fn body(mut self, pattern: Regex) -> Self {
+        self.body = Some(pattern);
+        self
+    }
+
+    ///
+    /// Sends the request over https.
+    ///
+    pub fn tls(mut self) -> Self {
+        self.tls = true;
+        self
+    }
+
+    ///
+    /// Sends the request over https, without verifying the certificate of the container.
+    ///
+    pub fn insecure(mut self) -> Self {
+        self.tls = true;
+        self.insecure = true;
+        self
+    }
+
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub(crate) fn accepts_status(&self, status: u16) -> bool {
+        if self.status_codes.is_empty() {
+            (200..300).contains(&status)
+        } else {
+            self.status_codes.contains(&status)
+        }
+    }
+}
+
+impl From<HttpWait> for WaitStrategy {
+    fn from(http: HttpWait) -> Self {
+        WaitStrategy::Http(Box::new(http))
+    }
+}
+
+pub(crate) fn gen_hash() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pub key: String,
+    pub value: String,
+}
+
+impl Label {
+    pub fn new(key: String, value: String) -> Self {
+        Self { key, value }
+    }
+}
+
+impl<K, V> From<(K, V)> for Label
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from(value: (K, V)) -> Self {
+        Label::new(value.0.into(), value.1.into())
+    }
+}
+
+///
+/// A user defined network containers can be attached to.
+///
+/// Containers in the same network can reach each other by their name or any of their network aliases.
+///
+/// ```
+/// use contain_rs_core::container::Network;
+///
+/// let network = Network::new("backend")
+///     .driver("bridge")
+///     .internal(true)
+///     .label("purpose", "testing");
+///
+/// assert_eq!(network.name, "backend");
+/// assert_eq!(network.driver.as_deref(), Some("bridge"));
+/// ```
+///
+#[derive(Clone, Debug)]
 pub struct Network {
-    // TODO
+    pub name: String,
+    pub driver: Option<String>,
+    pub internal: bool,
+    pub labels: Vec<Label>,
+}
+
+impl Network {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            driver: None,
+            internal: false,
+            labels: Vec::new(),
+        }
+    }
+
+    ///
+    /// Set the network driver, the runtime's default driver is used otherwise.
+    ///
+    pub fn driver(mut self, driver: &str) -> Self {
+        self.driver = Some(driver.into());
+        self
+    }
+
+    ///
+    /// Restrict external access to the network.
+    ///
+    pub fn internal(mut self, internal: bool) -> Self {
+        self.internal = internal;
+        self
+    }
+
+    pub fn label<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.labels.push((key, value).into());
+        self
+    }
+}
+
+impl Default for Network {
+    ///
+    /// Creates a network with a generated name.
+    ///
+    fn default() -> Self {
+        Self::new(&format!("contain-rs-{}", gen_hash()))
+    }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Port {
     pub number: String,
 }
 
+impl Port {
+    ///
+    /// The port number including its protocol, the way runtimes report it.
+    ///
+    /// ```
+    /// use contain_rs_core::container::Port;
+    ///
+    /// assert_eq!(Port::from(80).with_protocol(), "80/tcp");
+    /// assert_eq!(Port::from("53/udp").with_protocol(), "53/udp");
+    /// ```
+    ///
+    pub fn with_protocol(&self) -> String {
+        if self.number.contains('/') {
+            self.number.clone()
+        } else {
+            format!("{}/tcp", self.number)
+        }
+    }
+}
+
 impl<T> From<T> for Port
 where
     T: ToString,
@@ -125,7 +635,7 @@ pub struct PortMapping {
     pub target: Port,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct EnvVar {
     pub key: String,
     pub value: String,
@@ -199,6 +709,127 @@ impl From<&Image> for String {
     }
 }
 
+///
+/// The files an image is built from.
+///
+#[derive(Clone)]
+pub enum BuildContext {
+    ///
+    /// A directory on the host.
+    ///
+    Path(PathBuf),
+    ///
+    /// A tar archive held in memory.
+    ///
+    Archive(Vec<u8>),
+}
+
+///
+/// Describes how to build an image, see [crate::client::Client::build_image].
+///
+/// Layers are cached by the runtime, unless [ImageBuild::no_cache] is set.
+///
+/// ```
+/// use contain_rs_core::container::{Image, ImageBuild};
+/// use std::str::FromStr;
+///
+/// let build = ImageBuild::from_path("services/api")
+///     .dockerfile("docker/Dockerfile")
+///     .build_arg("VERSION", "1.0")
+///     .target("runtime")
+///     .tag(Image::from_str("localhost/api:test").unwrap());
+///
+/// assert_eq!(build.tags.len(), 1);
+/// ```
+///
+#[derive(Clone)]
+pub struct ImageBuild {
+    pub context: BuildContext,
+    pub dockerfile: Option<String>,
+    ///
+    /// Values for the `ARG`s of the Dockerfile, they are variables of the build.
+    ///
+    pub build_args: Vec<EnvVar>,
+    pub target: Option<String>,
+    pub tags: Vec<Image>,
+    pub no_cache: bool,
+}
+
+impl ImageBuild {
+    pub fn new(context: BuildContext) -> Self {
+        Self {
+            context,
+            dockerfile: None,
+            build_args: Vec::new(),
+            target: None,
+            tags: Vec::new(),
+            no_cache: false,
+        }
+    }
+
+    ///
+    /// Build from a context directory on the host.
+    ///
+    pub fn from_path(path: impl Into<PathBuf>) -> Self {
+        Self::new(BuildContext::Path(path.into()))
+    }
+
+    ///
+    /// Build from a tar archive containing the context.
+    ///
+    pub fn from_archive(archive: impl Into<Vec<u8>>) -> Self {
+        Self::new(BuildContext::Archive(archive.into()))
+    }
+
+    ///
+    /// The path of the Dockerfile relative to the context, `Dockerfile` is used otherwise.
+    ///
+    pub fn dockerfile(mut self, dockerfile: &str) -> Self {
+        self.dockerfile = Some(dockerfile.into());
+        self
+    }
+
+    pub fn build_arg<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.build_args.push((key, value).into());
+        self
+    }
+
+    ///
+    /// The stage of a multi stage build to build, the last one is built otherwise.
+    ///
+    pub fn target(mut self, target: &str) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    ///
+    /// Tag the built image, a name is generated when no tag is given.
+    ///
+    pub fn tag(mut self, image: Image) -> Self {
+        self.tags.push(image);
+        self
+    }
+
+    pub fn no_cache(mut self, no_cache: bool) -> Self {
+        self.no_cache = no_cache;
+        self
+    }
+
+    ///
+    /// The tags to apply to the built image, the first of which is the one that gets returned by a build.
+    ///
+    pub(crate) fn tags_or_generated(&self) -> Vec<Image> {
+        if self.tags.is_empty() {
+            vec![Image::from_name_and_tag(
+                &format!("localhost/contain-rs-{}", gen_hash().to_lowercase()),
+                "latest",
+            )]
+        } else {
+            self.tags.clone()
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Volume {
     Mount {
@@ -211,6 +842,134 @@ pub enum Volume {
     },
 }
 
+///
+/// Where the content of a [ContainerFile] comes from.
+///
+#[derive(Clone)]
+pub enum FileSource {
+    ///
+    /// A file or a directory on the host.
+    ///
+    Path(PathBuf),
+    ///
+    /// File content held in memory.
+    ///
+    Content(Vec<u8>),
+}
+
+///
+/// A file that is placed into a container before it starts.
+///
+/// Unlike [Volume::Mount] the content is transferred to the runtime,
+/// so this works even when the host path is not accessible to the daemon.
+///
+#[derive(Clone)]
+pub struct ContainerFile {
+    pub source: FileSource,
+    pub container_path: String,
+}
+
+///
+/// Decides when the image of a [Container] is pulled before it is run.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PullPolicy {
+    ///
+    /// Pull the image every time the container is run, picking up updates of moving tags.
+    ///
+    Always,
+    ///
+    /// Only pull the image in case it is not present locally.
+    ///
+    #[default]
+    IfNotPresent,
+    ///
+    /// Never pull the image, running the container fails when it is not present.
+    ///
+    Never,
+}
+
+///
+/// Limits on the resources a [Container] may use.
+///
+/// ```
+/// use contain_rs_core::container::Resources;
+///
+/// let resources = Resources::new()
+///     .memory(256 * 1024 * 1024)
+///     .cpus(1.5)
+///     .pids_limit(100)
+///     .ulimit("nofile", 1024, 2048);
+///
+/// assert_eq!(resources.memory, Some(268435456));
+/// ```
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Resources {
+    ///
+    /// The memory limit in bytes, the container gets OOM killed when exceeding it.
+    ///
+    pub memory: Option<u64>,
+    ///
+    /// The limit of memory plus swap in bytes, -1 allows unlimited swap.
+    ///
+    pub memory_swap: Option<i64>,
+    ///
+    /// How many cpus the container may use, fractions are allowed.
+    ///
+    pub cpus: Option<f64>,
+    ///
+    /// The maximum number of processes and threads inside the container.
+    ///
+    pub pids_limit: Option<u64>,
+    pub ulimits: Vec<Ulimit>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn memory(mut self, bytes: u64) -> Self {
+        self.memory = Some(bytes);
+        self
+    }
+
+    pub fn memory_swap(mut self, bytes: i64) -> Self {
+        self.memory_swap = Some(bytes);
+        self
+    }
+
+    pub fn cpus(mut self, cpus: f64) -> Self {
+        self.cpus = Some(cpus);
+        self
+    }
+
+    pub fn pids_limit(mut self, pids_limit: u64) -> Self {
+        self.pids_limit = Some(pids_limit);
+        self
+    }
+
+    ///
+    /// Limit a resource of the processes inside the container, e.g. `nofile` for the number of open files.
+    ///
+    pub fn ulimit(mut self, name: &str, soft: u64, hard: u64) -> Self {
+        self.ulimits.push(Ulimit {
+            name: name.into(),
+            soft,
+            hard,
+        });
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ulimit {
+    pub name: String,
+    pub soft: u64,
+    pub hard: u64,
+}
+
 ///
 /// A container makes up the schedulable unit of this crate.
 ///
@@ -220,40 +979,57 @@ pub enum Volume {
 pub struct Container {
     pub name: String,
     pub image: Image,
+    pub pull_policy: PullPolicy,
     pub command: Vec<String>,
     pub network: Option<Network>,
+    pub network_aliases: Vec<String>,
     pub volumes: Vec<Volume>,
+    pub files: Vec<ContainerFile>,
     pub port_mappings: Vec<PortMapping>,
+    ///
+    /// Container ports published on a host port chosen by the runtime, see [Container::expose_port].
+    ///
+    pub exposed_ports: Vec<Port>,
+    pub publish_all_ports: bool,
     pub env_vars: Vec<EnvVar>,
     pub health_check: Option<HealthCheck>,
     pub wait_strategy: Option<WaitStrategy>,
+    pub startup_timeout: Duration,
     pub additional_wait_period: Duration,
+    pub stop_timeout: Option<Duration>,
+    pub resources: Resources,
+    pub labels: Vec<Label>,
+    pub auto_remove: bool,
 }
 
 impl Container {
-    fn gen_hash() -> String {
-        rand::thread_rng()
-            .sample_iter(&Alphanumeric)
-            .take(8)
-            .map(char::from)
-            .collect()
-    }
+    pub const DEFAULT_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
 
     ///
     /// Creates a new container from and [Image]
     ///
     pub fn from_image(image: Image) -> Self {
         Container {
-            name: format!("contain-rs-{}", Self::gen_hash()),
+            name: format!("contain-rs-{}", gen_hash()),
             image,
+            pull_policy: PullPolicy::default(),
             command: Vec::new(),
             network: None,
+            network_aliases: Vec::new(),
             port_mappings: Vec::new(),
+            exposed_ports: Vec::new(),
+            publish_all_ports: false,
             env_vars: Vec::new(),
             volumes: Vec::new(),
+            files: Vec::new(),
             health_check: None,
             wait_strategy: None,
+            startup_timeout: Container::DEFAULT_STARTUP_TIMEOUT,
             additional_wait_period: Duration::from_secs(0),
+            stop_timeout: None,
+            resources: Resources::default(),
+            labels: Vec::new(),
+            auto_remove: false,
         }
     }
 
@@ -267,6 +1043,14 @@ impl Container {
         self
     }
 
+    ///
+    /// Define when the image is pulled, see [PullPolicy].
+    ///
+    pub fn pull_policy(&mut self, pull_policy: PullPolicy) -> &mut Self {
+        self.pull_policy = pull_policy;
+        self
+    }
+
     ///
     /// Define an explicit command to run in the container.
     ///
@@ -297,6 +1081,24 @@ impl Container {
         self
     }
 
+    ///
+    /// Attach the container to a [Network] when it is run.
+    ///
+    /// The network has to exist beforehand, see [crate::client::Client::create_network].
+    ///
+    pub fn network(&mut self, network: &Network) -> &mut Self {
+        self.network = Some(network.clone());
+        self
+    }
+
+    ///
+    /// Add an alias other containers in the same [Network] can reach this container by.
+    ///
+    pub fn network_alias<T: Into<String>>(&mut self, alias: T) -> &mut Self {
+        self.network_aliases.push(alias.into());
+        self
+    }
+
     pub fn volume(&mut self, name: &str, mount_point: &str) -> &mut Self {
         self.volumes.push(Volume::Named {
             name: name.to_string(),
@@ -315,6 +1117,30 @@ impl Container {
         self
     }
 
+    ///
+    /// Copy a file or directory from the host to `container_path` before the container starts.
+    ///
+    pub fn copy_file(&mut self, host_path: impl Into<PathBuf>, container_path: &str) -> &mut Self {
+        self.files.push(ContainerFile {
+            source: FileSource::Path(host_path.into()),
+            container_path: container_path.to_string(),
+        });
+
+        self
+    }
+
+    ///
+    /// Place a file with the given content at `container_path` before the container starts.
+    ///
+    pub fn copy_content(&mut self, content: impl Into<Vec<u8>>, container_path: &str) -> &mut Self {
+        self.files.push(ContainerFile {
+            source: FileSource::Content(content.into()),
+            container_path: container_path.to_string(),
+        });
+
+        self
+    }
+
     ///
     /// Map a port from `source` on the host to `target` in the container.
     ///
@@ -326,6 +1152,32 @@ impl Container {
         self
     }
 
+    ///
+    /// Expose `target` in the container on a host port chosen by the runtime.
+    ///
+    /// Use [crate::client::Handle::host_port] to find out which port was bound.
+    ///
+    pub fn expose_port(&mut self, target: impl Into<Port>) -> &mut Self {
+        self.exposed_ports.push(target.into());
+        self
+    }
+
+    ///
+    /// Let the runtime remove the container as soon as it exits, like `--rm` does.
+    ///
+    pub fn auto_remove(&mut self, auto_remove: bool) -> &mut Self {
+        self.auto_remove = auto_remove;
+        self
+    }
+
+    ///
+    /// Publish all ports exposed by the image on host ports chosen by the runtime.
+    ///
+    pub fn publish_all_ports(&mut self, publish_all_ports: bool) -> &mut Self {
+        self.publish_all_ports = publish_all_ports;
+        self
+    }
+
     ///
     /// Define an environment variable for the container.
     ///
@@ -342,6 +1194,16 @@ impl Container {
         self
     }
 
+    ///
+    /// Define how long waiting for the [WaitStrategy] may take, before giving up.
+    ///
+    /// Defaults to [Container::DEFAULT_STARTUP_TIMEOUT], use [WaitStrategy::with_timeout] to override it for a single strategy.
+    ///
+    pub fn startup_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
     ///
     /// Add some additional wait time for concidering the container healthy.
     ///
@@ -352,6 +1214,44 @@ impl Container {
         self
     }
 
+    ///
+    /// How long stopping or restarting the container waits for it to exit, before it gets killed.
+    ///
+    /// The runtime's default of 10 seconds applies if not set. The timeout is rounded up to whole seconds.
+    ///
+    pub fn stop_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.stop_timeout = Some(timeout);
+        self
+    }
+
+    ///
+    /// The stop timeout in the whole seconds runtimes take, rounded up so a short timeout does not kill right away.
+    ///
+    pub(crate) fn stop_timeout_secs(&self) -> Option<u64> {
+        self.stop_timeout
+            .map(|timeout| timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0))
+    }
+
+    ///
+    /// Add a label to the container.
+    ///
+    /// The [session labels](crate::session::session_labels) are added to every container automatically.
+    ///
+    pub fn label<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut Self {
+        self.labels.push((key, value).into());
+        self
+    }
+
+    ///
+    /// Limit the resources the container may use, e.g. to reproduce the limits of a production deployment.
+    ///
+    /// Whether a container got killed for exceeding its memory limit is reported by [inspecting](crate::rt::ContainerState::oom_killed) it.
+    ///
+    pub fn resources(&mut self, resources: Resources) -> &mut Self {
+        self.resources = resources;
+        self
+    }
+
     ///
     /// Add an arbitrary healthcheck to the container.
     ///
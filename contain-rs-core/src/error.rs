@@ -1,6 +1,9 @@
-use std::{io, process::Output};
+use std::{io, process::ExitStatus, string::FromUtf8Error, time::Duration};
 
-use crate::{container::WaitStrategy, rt::ContainerStatus};
+use crate::{
+    container::WaitStrategy,
+    rt::{ContainerState, ContainerStatus},
+};
 
 pub type ContainerResult<T> = std::result::Result<T, ContainersError>;
 
@@ -8,8 +11,25 @@ pub type ContainerResult<T> = std::result::Result<T, ContainersError>;
 pub enum ContainersError {
     #[error("IO Error")]
     IOError(#[from] io::Error),
-    #[error("Command exited with non zero exit-code")]
-    CommandError(Output),
+    #[error("Command `{command}` failed with {status}: {stderr}")]
+    CommandError {
+        command: String,
+        status: ExitStatus,
+        stdout: String,
+        stderr: String,
+    },
+    #[error("Image not found: {message}")]
+    ImageNotFound { message: String },
+    #[error("Port already allocated: {message}")]
+    PortAlreadyAllocated { message: String },
+    #[error("Container name already in use: {message}")]
+    NameConflict { message: String },
+    #[error("Container runtime not reachable: {message}")]
+    DaemonUnreachable { message: String },
+    #[error("Container runtime binary not found: {binary}")]
+    RuntimeNotFound { binary: String },
+    #[error("Output is not valid utf-8")]
+    Utf8Error(#[from] FromUtf8Error),
     #[error("Error parsing json")]
     JsonError(#[from] serde_json::Error),
     #[error("Unexpected container stauts: {status:?}")]
@@ -21,6 +41,39 @@ pub enum ContainersError {
         container_name: String,
         wait_strategy: WaitStrategy,
     },
+    #[error("Wait strategy {wait_strategy:?} failed: {source}")]
+    WaitStrategyFailed {
+        wait_strategy: WaitStrategy,
+        source: Box<ContainersError>,
+    },
+    #[error(
+        "Timed out after {timeout:?} waiting for container {container_name} ({wait_strategy:?}), last state: {last_state:?}, last log lines:\n{}",
+        .last_log_lines.join("\n")
+    )]
+    WaitTimeout {
+        container_name: String,
+        wait_strategy: WaitStrategy,
+        timeout: Duration,
+        last_state: Option<Box<ContainerState>>,
+        last_log_lines: Vec<String>,
+    },
+    #[error(
+        "Container {container_name} exited with code {exit_code} while waiting for it, last log lines:\n{}",
+        .last_log_lines.join("\n")
+    )]
+    ContainerExited {
+        container_name: String,
+        exit_code: i32,
+        last_log_lines: Vec<String>,
+    },
     #[error("Invalid image name: {name}")]
     InvalidImageName { name: String },
+    #[error("Invalid port: {port}")]
+    InvalidPort { port: String },
+    #[error("Invalid api endpoint: {endpoint}")]
+    InvalidEndpoint { endpoint: String },
+    #[error("Api request failed with status {status}: {message}")]
+    ApiError { status: u16, message: String },
+    #[error("Reaper failed: {message}")]
+    ReaperFailed { message: String },
 }
@@ -2,4 +2,5 @@ pub mod client;
 pub mod container;
 pub mod error;
 pub mod rt;
+pub mod session;
 pub use regex::Regex;
@@ -1,17 +1,204 @@
-use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::container::Port;
+
+///
+/// The details of a container, as reported by inspecting it with either docker or podman.
+///
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DetailedContainerInfo {
     #[serde(alias = "Id")]
     pub id: String,
+    ///
+    /// The name of the container, docker prefixes it with a `/`.
+    ///
+    #[serde(alias = "Name", default)]
+    pub name: String,
+    #[serde(alias = "Created", default)]
+    pub created: String,
+    ///
+    /// The id of the image the container was created from.
+    ///
+    #[serde(alias = "Image", default)]
+    pub image_id: String,
+    ///
+    /// The digest of the image manifest the container was created from.
+    ///
+    /// Podman reports it with the container, the docker clients look it up in the `RepoDigests` of the image.
+    /// It stays empty for images that were built locally and never pushed or pulled.
+    ///
+    #[serde(alias = "ImageDigest", default)]
+    pub image_digest: Option<String>,
+    #[serde(alias = "RestartCount", default)]
+    pub restart_count: u32,
     #[serde(alias = "State")]
     pub state: ContainerState,
+    #[serde(alias = "Config", default)]
+    pub config: ContainerConfig,
+    #[serde(alias = "NetworkSettings", default)]
+    pub network_settings: NetworkSettings,
+    #[serde(alias = "Mounts", default, deserialize_with = "null_as_default")]
+    pub mounts: Vec<Mount>,
+}
+
+impl DetailedContainerInfo {
+    ///
+    /// The host port a container port has been published on.
+    ///
+    /// ```
+    /// use contain_rs_core::rt::DetailedContainerInfo;
+    ///
+    /// let info: DetailedContainerInfo = serde_json::from_str(r#"{
+    ///     "Id": "abc",
+    ///     "State": { "Running": true },
+    ///     "NetworkSettings": {
+    ///         "Ports": {
+    ///             "80/tcp": [{ "HostIp": "0.0.0.0", "HostPort": "32768" }],
+    ///             "443/tcp": null
+    ///         }
+    ///     }
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(info.host_port(80), Some(32768));
+    /// assert_eq!(info.host_port(443), None);
+    /// ```
+    ///
+    pub fn host_port(&self, port: impl Into<Port>) -> Option<u16> {
+        self.port_binding(port)
+            .and_then(|binding| binding.host_port.parse().ok())
+    }
+
+    ///
+    /// The binding of the host port a container port is published on, with the host ip it is bound to.
+    ///
+    pub fn port_binding(&self, port: impl Into<Port>) -> Option<&PortBinding> {
+        self.network_settings
+            .ports
+            .get(&port.into().with_protocol())
+            .and_then(|bindings| bindings.as_ref())
+            .and_then(|bindings| {
+                bindings
+                    .iter()
+                    .find(|binding| binding.host_port.parse::<u16>().is_ok())
+            })
+    }
+
+    ///
+    /// The ip address of the container in a network.
+    ///
+    pub fn ip_address(&self, network: &str) -> Option<&str> {
+        self.network_settings
+            .networks
+            .get(network)
+            .map(|network| network.ip_address.as_str())
+            .filter(|ip_address| !ip_address.is_empty())
+    }
+
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.config.labels
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ContainerConfig {
+    #[serde(alias = "Hostname", default)]
+    pub hostname: String,
+    ///
+    /// The image as it was referenced when creating the container.
+    ///
+    #[serde(alias = "Image", default)]
+    pub image: String,
+    #[serde(alias = "Env", default, deserialize_with = "null_as_default")]
+    pub env: Vec<String>,
+    #[serde(alias = "Labels", default, deserialize_with = "null_as_default")]
+    pub labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct NetworkSettings {
+    ///
+    /// Published ports by container port and protocol, e.g. `80/tcp`.
+    ///
+    #[serde(alias = "Ports", default, deserialize_with = "null_as_default")]
+    pub ports: HashMap<String, Option<Vec<PortBinding>>>,
+    ///
+    /// The networks the container is connected to, by network name.
+    ///
+    #[serde(alias = "Networks", default, deserialize_with = "null_as_default")]
+    pub networks: HashMap<String, NetworkEndpoint>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct NetworkEndpoint {
+    #[serde(alias = "NetworkID", default)]
+    pub network_id: String,
+    #[serde(alias = "IPAddress", default)]
+    pub ip_address: String,
+    #[serde(alias = "Gateway", default)]
+    pub gateway: String,
+    #[serde(alias = "MacAddress", default)]
+    pub mac_address: String,
+    #[serde(alias = "Aliases", default, deserialize_with = "null_as_default")]
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PortBinding {
+    #[serde(alias = "HostIp", default)]
+    pub host_ip: String,
+    #[serde(alias = "HostPort")]
+    pub host_port: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Mount {
+    ///
+    /// The kind of mount, e.g. `bind` or `volume`.
+    ///
+    #[serde(alias = "Type", default)]
+    pub kind: String,
+    ///
+    /// The name of the volume, empty for bind mounts.
+    ///
+    #[serde(alias = "Name", default)]
+    pub name: String,
+    #[serde(alias = "Source", default)]
+    pub source: String,
+    #[serde(alias = "Destination")]
+    pub destination: String,
+    #[serde(alias = "RW", default)]
+    pub rw: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ContainerState {
+    ///
+    /// The lifecycle status of the container, e.g. `created`, `running` or `exited`.
+    ///
+    #[serde(alias = "Status", default)]
+    pub status: String,
     #[serde(alias = "Running")]
     pub running: bool,
+    #[serde(alias = "Paused", default)]
+    pub paused: bool,
+    #[serde(alias = "Restarting", default)]
+    pub restarting: bool,
+    #[serde(alias = "OOMKilled", default)]
+    pub oom_killed: bool,
+    #[serde(alias = "ExitCode", default)]
+    pub exit_code: i32,
+    ///
+    /// When the container was last started as RFC 3339 timestamp, `0001-01-01T00:00:00Z` if it never was.
+    ///
+    #[serde(alias = "StartedAt", default)]
+    pub started_at: String,
+    ///
+    /// When the container last stopped as RFC 3339 timestamp, `0001-01-01T00:00:00Z` if it never did.
+    ///
+    #[serde(alias = "FinishedAt", default)]
+    pub finished_at: String,
     // currently these are used for poth docker and podman
     #[serde(alias = "Healthcheck", alias = "Health")]
     pub health: Option<HealthCheck>,
@@ -47,3 +234,231 @@ pub enum ContainerStatus {
     #[serde(alias = "unhealthy")]
     Unhealthy,
 }
+
+///
+/// The result of a command executed inside a container.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    ///
+    /// The exit code of the command, or none in case it was terminated by a signal.
+    ///
+    pub exit_code: Option<i32>,
+}
+
+impl ExecOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+///
+/// The details of a network, as reported by inspecting it with either docker or podman.
+///
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NetworkInfo {
+    #[serde(alias = "Name")]
+    pub name: String,
+    #[serde(alias = "Id", default)]
+    pub id: String,
+    #[serde(alias = "Driver", default)]
+    pub driver: String,
+    #[serde(alias = "Labels", default, deserialize_with = "null_as_default")]
+    pub labels: HashMap<String, String>,
+}
+
+///
+/// The details of an image needed by the clients, as reported by inspecting it with docker.
+///
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ImageInfo {
+    #[serde(alias = "Id", default)]
+    pub id: String,
+    ///
+    /// The references of the image in the registries it was pulled from or pushed to, e.g. `postgres@sha256:...`.
+    ///
+    #[serde(alias = "RepoDigests", default, deserialize_with = "null_as_default")]
+    pub repo_digests: Vec<String>,
+}
+
+impl ImageInfo {
+    ///
+    /// The manifest digest of the image, taken from its first repo digest.
+    ///
+    /// ```
+    /// use contain_rs_core::rt::ImageInfo;
+    ///
+    /// let info: ImageInfo = serde_json::from_str(r#"{
+    ///     "Id": "sha256:b2e1",
+    ///     "RepoDigests": ["postgres@sha256:7d3e"]
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(info.digest(), Some("sha256:7d3e"));
+    /// ```
+    ///
+    pub fn digest(&self) -> Option<&str> {
+        self.repo_digests
+            .first()
+            .and_then(|reference| reference.split_once('@'))
+            .map(|(_, digest)| digest)
+    }
+}
+
+///
+/// Something that happened to a container, as reported by the events of the runtime.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub container_id: String,
+    pub container_name: String,
+    ///
+    /// Further details of the event, e.g. the image and labels of the container.
+    ///
+    pub attributes: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    Create,
+    Start,
+    HealthStatus(ContainerStatus),
+    ///
+    /// The main process of the container exited.
+    ///
+    Die {
+        exit_code: Option<i32>,
+    },
+    Oom,
+    Destroy,
+    ///
+    /// Any other action, e.g. `stop`, `kill` or `exec_start`.
+    ///
+    Other(String),
+}
+
+impl Event {
+    ///
+    /// Parses a single event as printed by `docker events` or `podman events` in json format,
+    /// or as streamed by the events endpoint of the engine apis.
+    ///
+    /// Events that are not about a container result in none.
+    ///
+    /// ```
+    /// use contain_rs_core::rt::{ContainerStatus, Event, EventKind};
+    ///
+    /// let event = Event::from_json(r#"{
+    ///     "Type": "container",
+    ///     "Action": "health_status: healthy",
+    ///     "Actor": { "ID": "abc", "Attributes": { "name": "postgres" } }
+    /// }"#).unwrap().unwrap();
+    ///
+    /// assert_eq!(event.kind, EventKind::HealthStatus(ContainerStatus::Healthy));
+    /// assert_eq!(event.container_name, "postgres");
+    /// ```
+    ///
+    pub fn from_json(json: &str) -> serde_json::Result<Option<Self>> {
+        let raw: RawEvent = serde_json::from_str(json)?;
+
+        if raw.kind != "container" {
+            return Ok(None);
+        }
+
+        let mut attributes = raw.attributes;
+        let mut container_id = raw.id;
+
+        if let Some(actor) = raw.actor {
+            attributes.extend(actor.attributes);
+            container_id = actor.id;
+        }
+
+        // docker appends details to some actions, e.g. `health_status: healthy`
+        let (action, detail) = match raw.action.split_once(':') {
+            Some((action, detail)) => (action.trim(), detail.trim()),
+            None => (raw.action.trim(), ""),
+        };
+
+        let kind = match action {
+            "create" => EventKind::Create,
+            "start" => EventKind::Start,
+            "health_status" => {
+                let status = [detail, raw.health_status.as_str()]
+                    .into_iter()
+                    .chain(attributes.get("health_status").map(String::as_str))
+                    .find(|status| !status.is_empty())
+                    .unwrap_or_default();
+
+                EventKind::HealthStatus(serde_json::from_value(status.into())?)
+            }
+            "die" | "died" => EventKind::Die {
+                exit_code: raw.container_exit_code.or_else(|| {
+                    ["exitCode", "containerExitCode"]
+                        .into_iter()
+                        .find_map(|key| attributes.get(key)?.parse().ok())
+                }),
+            },
+            "oom" => EventKind::Oom,
+            "destroy" | "remove" => EventKind::Destroy,
+            other => EventKind::Other(other.to_string()),
+        };
+
+        let container_name = if raw.name.is_empty() {
+            attributes.get("name").cloned().unwrap_or_default()
+        } else {
+            raw.name
+        };
+
+        Ok(Some(Self {
+            kind,
+            container_id,
+            container_name,
+            attributes,
+        }))
+    }
+}
+
+///
+/// The fields of both event formats: docker nests the container as `Actor`, podman reports it at the top level.
+///
+/// Docker also sends the deprecated lowercase `status` and `id` fields, which must not be aliased here.
+///
+#[derive(Deserialize)]
+struct RawEvent {
+    #[serde(alias = "Type", default)]
+    kind: String,
+    #[serde(alias = "Action", alias = "Status", default)]
+    action: String,
+    #[serde(alias = "Actor")]
+    actor: Option<Actor>,
+    #[serde(alias = "ID", default)]
+    id: String,
+    #[serde(alias = "Name", default)]
+    name: String,
+    #[serde(alias = "Attributes", default, deserialize_with = "null_as_default")]
+    attributes: HashMap<String, String>,
+    #[serde(alias = "HealthStatus", default)]
+    health_status: String,
+    #[serde(alias = "ContainerExitCode")]
+    container_exit_code: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct Actor {
+    #[serde(alias = "ID", default)]
+    id: String,
+    #[serde(alias = "Attributes", default, deserialize_with = "null_as_default")]
+    attributes: HashMap<String, String>,
+}
+
+///
+/// Runtimes report empty collections as `null` at times.
+///
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -0,0 +1,153 @@
+//!
+//! Identifies the resources created by contain-rs.
+//!
+//! Every container and network gets labelled with the id of the session, the pid of the creating process,
+//! the host the pid belongs to and the crate version. This allows [Client::prune_orphans](crate::client::Client::prune_orphans)
+//! to find resources that were leaked by a process that got killed before it could clean up.
+//!
+
+use std::{collections::HashMap, fs};
+
+use lazy_static::lazy_static;
+
+use crate::container::{gen_hash, Label};
+
+pub const SESSION_LABEL: &str = "contain-rs.session";
+pub const PID_LABEL: &str = "contain-rs.pid";
+pub const HOST_LABEL: &str = "contain-rs.host";
+pub const VERSION_LABEL: &str = "contain-rs.version";
+pub const REAPER_LABEL: &str = "contain-rs.reaper";
+
+lazy_static! {
+    static ref SESSION_ID: String = gen_hash();
+    static ref HOST_ID: String = read_host_id();
+}
+
+///
+/// The id of the current session, there is one session per process.
+///
+pub fn session_id() -> &'static str {
+    &SESSION_ID
+}
+
+///
+/// Identifies the pid namespace of the current process, pids are only comparable within the same host id.
+///
+/// Processes in different containers sharing the socket of the runtime, e.g. ci jobs, get different ids.
+/// On linux it's the boot id combined with the pid namespace, elsewhere the hostname.
+///
+pub fn host_id() -> &'static str {
+    &HOST_ID
+}
+
+fn read_host_id() -> String {
+    let boot_id = fs::read_to_string("/proc/sys/kernel/random/boot_id");
+    let pid_namespace = fs::read_link("/proc/self/ns/pid");
+
+    match (boot_id, pid_namespace) {
+        (Ok(boot_id), Ok(pid_namespace)) => {
+            format!("{}-{}", boot_id.trim(), pid_namespace.display())
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+                .collect()
+        }
+        _ => hostname(),
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+
+    if result != 0 {
+        return "unknown".to_string();
+    }
+
+    let end = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+
+    String::from_utf8_lossy(&buffer[..end]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+///
+/// The labels contain-rs puts on every resource it creates.
+///
+/// ```
+/// use contain_rs_core::session::{session_id, session_labels, SESSION_LABEL};
+///
+/// let labels = session_labels();
+///
+/// assert!(labels.iter().any(|label| label.key == SESSION_LABEL && label.value == session_id()));
+/// ```
+///
+pub fn session_labels() -> Vec<Label> {
+    vec![
+        (SESSION_LABEL, session_id()).into(),
+        (PID_LABEL, std::process::id().to_string()).into(),
+        (HOST_LABEL, host_id()).into(),
+        (VERSION_LABEL, env!("CARGO_PKG_VERSION")).into(),
+    ]
+}
+
+///
+/// User defined labels followed by the session labels, which take precedence as the runtimes apply the last one.
+///
+/// The [reaper](crate::client::reaper) does not get the session label, otherwise it would remove itself
+/// before it is done with the rest of the session.
+///
+pub(crate) fn with_session_labels(labels: &[Label]) -> Vec<Label> {
+    let reaper = labels.iter().any(|label| label.key == REAPER_LABEL);
+
+    labels
+        .iter()
+        .cloned()
+        .chain(
+            session_labels()
+                .into_iter()
+                .filter(|label| !reaper || label.key != SESSION_LABEL),
+        )
+        .collect()
+}
+
+///
+/// Whether a resource was created by another contain-rs process that is gone by now.
+///
+/// Resources without a pid label were not created by contain-rs and are never considered orphans.
+/// The pid can only be checked for resources of the same [host](host_id), resources created by
+/// processes in other containers or on other machines sharing the runtime are never considered orphans.
+///
+pub(crate) fn is_orphan(labels: &HashMap<String, String>) -> bool {
+    let Some(pid) = labels.get(PID_LABEL).and_then(|pid| pid.parse().ok()) else {
+        return false;
+    };
+
+    if labels.get(HOST_LABEL).map(String::as_str) != Some(host_id()) {
+        return false;
+    }
+
+    labels.get(SESSION_LABEL).map(String::as_str) != Some(session_id()) && !process_alive(pid)
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+
+    // signal 0 only checks whether the process exists, EPERM means it belongs to another user
+    let result = unsafe { libc::kill(pid, 0) };
+
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    // without a way to check, resources are rather kept than removed while still in use
+    true
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "async")]
+
+use contain_rs_core::{
+    client::async_client::{AsyncClient, AsyncDocker, AsyncHandle, AsyncPodman},
+    container::{Container, HealthCheck, Image, WaitStrategy},
+};
+use rstest::*;
+use tokio::time::timeout;
+
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+#[fixture]
+fn podman() -> AsyncPodman {
+    AsyncPodman::new()
+}
+
+#[fixture]
+fn docker() -> AsyncDocker {
+    AsyncDocker::new()
+}
+
+#[rstest]
+#[case::podman_async_wait_for_log(podman())]
+#[case::docker_async_wait_for_log(docker())]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_wait_for_log(#[case] client: impl AsyncClient) {
+    let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+
+    container.wait_for(WaitStrategy::LogMessage {
+        pattern: regex::Regex::from_str("ready for start up").unwrap(),
+    });
+
+    let handle = client.create(container);
+
+    handle.run_and_wait().await.unwrap();
+
+    assert!(handle.is_running().await.unwrap());
+
+    handle.rm().await.unwrap();
+
+    assert!(!handle.exists().await.unwrap());
+}
+
+#[rstest]
+#[case::podman_async_wait_for_healthcheck(podman())]
+#[case::docker_async_wait_for_healthcheck(docker())]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_wait_for_health_check(#[case] client: impl AsyncClient) {
+    let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+
+    container
+        .health_check(HealthCheck::new("curl http://localhost || exit 1"))
+        .wait_for(WaitStrategy::HealthCheck);
+
+    let handle = client.create(container);
+
+    handle.run_and_wait().await.unwrap();
+
+    // the handle removes the container when dropped
+}
+
+#[rstest]
+#[case::podman_async_drop_current_thread(podman())]
+#[case::docker_async_drop_current_thread(docker())]
+#[tokio::test]
+async fn test_drop_on_current_thread_runtime(#[case] client: impl AsyncClient) {
+    let container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+    let name = container.name.clone();
+
+    {
+        let handle = client.create(container);
+        handle.run().await.unwrap();
+    }
+
+    let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+    container.name(&name);
+
+    assert!(!client.exists(&container).await.unwrap());
+}
+
+#[rstest]
+#[case::podman_async_cancel_wait(podman())]
+#[case::docker_async_cancel_wait(docker())]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cancel_wait(#[case] client: impl AsyncClient) {
+    let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+
+    container
+        .wait_for(WaitStrategy::log_message(
+            regex::Regex::from_str("never printed").unwrap(),
+        ))
+        .startup_timeout(Duration::from_secs(60));
+
+    let handle = client.create(container);
+
+    handle.run().await.unwrap();
+
+    let started = Instant::now();
+
+    assert!(timeout(Duration::from_millis(500), handle.wait())
+        .await
+        .is_err());
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    handle.rm().await.unwrap();
+}
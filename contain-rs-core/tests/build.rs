@@ -0,0 +1,83 @@
+use contain_rs_core::{
+    client::{docker::Docker, podman::Podman, Client, Handle},
+    container::{Container, Image, ImageBuild, WaitStrategy},
+    error::ContainersError,
+};
+use rstest::*;
+
+use std::str::FromStr;
+
+#[fixture]
+fn podman() -> Podman {
+    Podman::new()
+}
+
+#[fixture]
+fn docker() -> Docker {
+    Docker::new()
+}
+
+fn context(dockerfile: &str) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+
+    header.set_size(dockerfile.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+
+    builder
+        .append_data(&mut header, "docker/Dockerfile", dockerfile.as_bytes())
+        .unwrap();
+
+    builder.into_inner().unwrap()
+}
+
+#[rstest]
+#[case::podman_build_image(podman())]
+#[case::docker_build_image(docker())]
+fn test_build_image(#[case] client: impl Client) {
+    let dockerfile = "FROM docker.io/library/alpine AS base\n\
+        ARG GREETING\n\
+        FROM base AS runtime\n\
+        ARG GREETING\n\
+        CMD echo \"$GREETING\" && sleep 300\n";
+
+    let mut output = Vec::new();
+
+    let image = client
+        .build_image(
+            &ImageBuild::from_archive(context(dockerfile))
+                .dockerfile("docker/Dockerfile")
+                .build_arg("GREETING", "hello from the build")
+                .target("runtime")
+                .tag(Image::from_str("localhost/contain-rs-build-test").unwrap()),
+            |line| output.push(line.to_string()),
+        )
+        .unwrap();
+
+    assert!(!output.is_empty());
+    assert!(client.image_exists(&image).unwrap());
+
+    let mut container = Container::from_image(image);
+
+    container.wait_for(WaitStrategy::LogMessage {
+        pattern: regex::Regex::new("hello from the build").unwrap(),
+    });
+
+    let handle = client.create(container);
+
+    handle.run_and_wait().unwrap();
+}
+
+#[rstest]
+#[case::podman_build_failure(podman())]
+#[case::docker_build_failure(docker())]
+fn test_build_failure(#[case] client: impl Client) {
+    let result = client.build_image(
+        &ImageBuild::from_archive(context("FROM docker.io/library/alpine\nRUN exit 3\n"))
+            .dockerfile("docker/Dockerfile"),
+        |_| {},
+    );
+
+    assert!(matches!(result, Err(ContainersError::CommandError { .. })));
+}
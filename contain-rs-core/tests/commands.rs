@@ -0,0 +1,133 @@
+use std::{path::Path, process::Command, str::FromStr, time::Duration};
+
+use contain_rs_core::{
+    client::shared::{
+        build_copy_from_command, build_copy_to_command, build_kill_command, build_run_command,
+        build_stop_command,
+    },
+    container::{Container, Image, Resources},
+    session::{host_id, session_id},
+};
+
+fn args(command: &Command) -> Vec<String> {
+    command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
+fn nginx() -> Container {
+    let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+    container.name("nginx");
+    container
+}
+
+#[test]
+fn test_run_command_resources() {
+    let mut container = nginx();
+
+    container.label("com.example.stack", "backend").resources(
+        Resources::new()
+            .memory(64 * 1024 * 1024)
+            .memory_swap(-1)
+            .cpus(1.5)
+            .pids_limit(100)
+            .ulimit("nofile", 1024, 2048),
+    );
+
+    let mut command = Command::new("docker");
+    build_run_command(&mut command, &container);
+
+    assert_eq!(
+        args(&command),
+        vec![
+            "run".to_string(),
+            "-d".into(),
+            "--name".into(),
+            "nginx".into(),
+            "--label".into(),
+            "com.example.stack=backend".into(),
+            "--label".into(),
+            format!("contain-rs.session={}", session_id()),
+            "--label".into(),
+            format!("contain-rs.pid={}", std::process::id()),
+            "--label".into(),
+            format!("contain-rs.host={}", host_id()),
+            "--label".into(),
+            format!("contain-rs.version={}", env!("CARGO_PKG_VERSION")),
+            "--memory=67108864".into(),
+            "--memory-swap=-1".into(),
+            "--cpus=1.5".into(),
+            "--pids-limit=100".into(),
+            "--ulimit".into(),
+            "nofile=1024:2048".into(),
+            "docker.io/library/nginx:latest".into(),
+        ]
+    );
+}
+
+#[test]
+fn test_stop_and_kill_commands() {
+    let mut container = nginx();
+
+    let mut command = Command::new("podman");
+    build_stop_command(&mut command, &container);
+
+    assert_eq!(args(&command), vec!["stop", "nginx"]);
+
+    container.stop_timeout(Duration::from_secs(30));
+
+    let mut command = Command::new("podman");
+    build_stop_command(&mut command, &container);
+
+    assert_eq!(args(&command), vec!["stop", "-t", "30", "nginx"]);
+
+    // parts of a second are rounded up, instead of killing right away
+    container.stop_timeout(Duration::from_millis(500));
+
+    let mut command = Command::new("podman");
+    build_stop_command(&mut command, &container);
+
+    assert_eq!(args(&command), vec!["stop", "-t", "1", "nginx"]);
+
+    let mut command = Command::new("podman");
+    build_kill_command(&mut command, &container, "SIGHUP");
+
+    assert_eq!(args(&command), vec!["kill", "--signal", "SIGHUP", "nginx"]);
+}
+
+#[test]
+fn test_copy_into_existing_directory_commands() {
+    let container = nginx();
+
+    // `cp` places the copied path inside an existing target directory, the api clients do the same
+    let mut command = Command::new("docker");
+    build_copy_to_command(
+        &mut command,
+        &container,
+        Path::new("fixtures/init.sql"),
+        "/docker-entrypoint-initdb.d",
+    );
+
+    assert_eq!(
+        args(&command),
+        vec![
+            "cp",
+            "fixtures/init.sql",
+            "nginx:/docker-entrypoint-initdb.d"
+        ]
+    );
+
+    let mut command = Command::new("docker");
+    build_copy_from_command(
+        &mut command,
+        &container,
+        "/tmp/report.txt",
+        Path::new("reports"),
+    );
+
+    assert_eq!(
+        args(&command),
+        vec!["cp", "nginx:/tmp/report.txt", "reports"]
+    );
+}
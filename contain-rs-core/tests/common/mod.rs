@@ -0,0 +1,413 @@
+//!
+//! A fake container engine api served on a temporary unix socket.
+//!
+//! The same server can listen on a local tcp port, to fake http endpoints of containers.
+//! [FakeReaper] stands in for the ryuk sidecar.
+//!
+
+#![allow(dead_code)]
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener},
+    os::unix::net::UnixListener,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde_json::Value;
+
+static SOCKET_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Option<Value>,
+    pub raw: Vec<u8>,
+}
+
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub chunked: bool,
+    pub held_open: bool,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string().into_bytes(),
+            chunked: false,
+            held_open: false,
+        }
+    }
+
+    pub fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            chunked: false,
+            held_open: false,
+        }
+    }
+
+    pub fn raw(status: u16, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body,
+            chunked: false,
+            held_open: false,
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn chunked(status: u16, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body,
+            chunked: true,
+            held_open: false,
+        }
+    }
+
+    ///
+    /// A chunked body that is not finished, the connection is held open until the client closes it.
+    ///
+    pub fn held_open(status: u16, body: Vec<u8>) -> Self {
+        Self {
+            held_open: true,
+            ..Self::chunked(status, body)
+        }
+    }
+}
+
+enum Address {
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+}
+
+pub struct FakeEngine {
+    address: Address,
+    requests: Arc<Mutex<Vec<Request>>>,
+    open_connections: Arc<AtomicUsize>,
+}
+
+type Handler = Arc<dyn Fn(&Request) -> Reply + Send + Sync>;
+
+impl FakeEngine {
+    pub fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> Reply + Send + Sync + 'static,
+    {
+        let path = std::env::temp_dir().join(format!(
+            "contain-rs-test-{}-{}.sock",
+            std::process::id(),
+            SOCKET_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        let _ = std::fs::remove_file(&path);
+
+        let listener = UnixListener::bind(&path).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Handler = Arc::new(handler);
+        let recorded = requests.clone();
+        let open_connections = Arc::new(AtomicUsize::new(0));
+        let open = open_connections.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { return };
+                let (handler, recorded, open) = (handler.clone(), recorded.clone(), open.clone());
+                let reader = BufReader::new(stream.try_clone().unwrap());
+
+                thread::spawn(move || serve(reader, stream, handler.as_ref(), &recorded, &open));
+            }
+        });
+
+        Self {
+            address: Address::Unix(path),
+            requests,
+            open_connections,
+        }
+    }
+
+    pub fn start_tcp<F>(handler: F) -> Self
+    where
+        F: Fn(&Request) -> Reply + Send + Sync + 'static,
+    {
+        Self::start_tcp_on("127.0.0.1", handler)
+    }
+
+    ///
+    /// Listens on another loopback address, to stand in for a remote machine.
+    ///
+    pub fn start_tcp_on<F>(ip: &str, handler: F) -> Self
+    where
+        F: Fn(&Request) -> Reply + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind((ip, 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Handler = Arc::new(handler);
+        let recorded = requests.clone();
+        let open_connections = Arc::new(AtomicUsize::new(0));
+        let open = open_connections.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { return };
+                let (handler, recorded, open) = (handler.clone(), recorded.clone(), open.clone());
+                let reader = BufReader::new(stream.try_clone().unwrap());
+
+                thread::spawn(move || serve(reader, stream, handler.as_ref(), &recorded, &open));
+            }
+        });
+
+        Self {
+            address: Address::Tcp(address),
+            requests,
+            open_connections,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        match self.address {
+            Address::Tcp(address) => address.port(),
+            Address::Unix(_) => panic!("Fake engine does not listen on a tcp port"),
+        }
+    }
+
+    pub fn endpoint(&self) -> String {
+        match &self.address {
+            Address::Unix(path) => format!("unix://{}", path.display()),
+            Address::Tcp(address) => format!("tcp://{address}"),
+        }
+    }
+
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    ///
+    /// How many connections of [held open replies](Reply::held_open) the clients did not close yet.
+    ///
+    pub fn open_connections(&self) -> usize {
+        self.open_connections.load(Ordering::SeqCst)
+    }
+
+    pub fn request_lines(&self) -> Vec<String> {
+        self.requests()
+            .iter()
+            .map(|request| format!("{} {}", request.method, request.path))
+            .collect()
+    }
+}
+
+impl Drop for FakeEngine {
+    fn drop(&mut self) {
+        if let Address::Unix(path) = &self.address {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn serve(
+    mut reader: impl BufRead,
+    mut stream: impl Write,
+    handler: &(dyn Fn(&Request) -> Reply + Send + Sync),
+    recorded: &Mutex<Vec<Request>>,
+    open_connections: &AtomicUsize,
+) {
+    let mut line = String::new();
+
+    reader.read_line(&mut line).unwrap();
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+
+        let header = header.trim_end();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap();
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    let request = Request {
+        method,
+        path,
+        body: serde_json::from_slice(&body).ok(),
+        raw: body,
+    };
+
+    recorded.lock().unwrap().push(request.clone());
+
+    let reply = handler(&request);
+
+    if reply.held_open {
+        open_connections.fetch_add(1, Ordering::SeqCst);
+    }
+
+    let headers: String = reply
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect();
+
+    let head = if reply.chunked {
+        format!(
+            "HTTP/1.1 {} Fake\r\n{headers}Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            reply.status
+        )
+    } else {
+        format!(
+            "HTTP/1.1 {} Fake\r\n{headers}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            reply.status,
+            reply.body.len()
+        )
+    };
+
+    let _ = stream.write_all(head.as_bytes());
+
+    if reply.chunked {
+        for chunk in reply.body.chunks(7) {
+            let _ = stream.write_all(format!("{:x}\r\n", chunk.len()).as_bytes());
+            let _ = stream.write_all(chunk);
+            let _ = stream.write_all(b"\r\n");
+        }
+
+        if reply.held_open {
+            let _ = stream.flush();
+            let _ = reader.read_to_end(&mut Vec::new());
+
+            open_connections.fetch_sub(1, Ordering::SeqCst);
+
+            return;
+        }
+
+        let _ = stream.write_all(b"0\r\n\r\n");
+    } else {
+        let _ = stream.write_all(&reply.body);
+    }
+
+    let _ = stream.flush();
+}
+
+///
+/// Decodes a percent encoded path segment or query value.
+///
+pub fn decode(value: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = value.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail) {
+            (b'%', [high, low, tail @ ..]) => {
+                let hex = std::str::from_utf8(&[*high, *low]).unwrap().to_string();
+                bytes.push(u8::from_str_radix(&hex, 16).unwrap());
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8(bytes).unwrap()
+}
+
+///
+/// Frames a log line the way the engine does for containers without a tty.
+///
+pub fn log_frame(stream: u8, line: &str) -> Vec<u8> {
+    let mut frame = vec![stream, 0, 0, 0];
+    frame.extend_from_slice(&(line.len() as u32).to_be_bytes());
+    frame.extend_from_slice(line.as_bytes());
+    frame
+}
+
+///
+/// A fake reaper, answering the filter of each connection in turn and reporting the filter once the connection dropped.
+///
+/// An empty answer closes the connection right away, like a port that is published before ryuk listens.
+///
+pub struct FakeReaper {
+    port: u16,
+    disconnected: Receiver<String>,
+}
+
+impl FakeReaper {
+    pub fn start(answers: &[&str]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let answers: Vec<String> = answers.iter().map(|answer| answer.to_string()).collect();
+        let (sender, disconnected) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (stream, answer) in listener.incoming().zip(answers) {
+                let Ok(mut stream) = stream else { return };
+
+                if answer.is_empty() {
+                    continue;
+                }
+
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut filter = String::new();
+
+                reader.read_line(&mut filter).unwrap();
+                stream.write_all(format!("{answer}\n").as_bytes()).unwrap();
+
+                // the connection is held until the client is gone
+                let _ = reader.read_to_end(&mut Vec::new());
+                let _ = sender.send(filter.trim_end().to_string());
+            }
+        });
+
+        Self { port, disconnected }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn address(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+
+    pub fn disconnected_filter(&self) -> Option<String> {
+        self.disconnected.recv_timeout(Duration::from_secs(5)).ok()
+    }
+}
@@ -0,0 +1,95 @@
+use contain_rs_core::{
+    client::{docker::Docker, podman::Podman, Client, Handle},
+    container::{Container, Exec, Image, WaitStrategy},
+};
+use rstest::*;
+
+use std::{fs, path::PathBuf, str::FromStr, time::Duration};
+
+#[fixture]
+fn podman() -> Podman {
+    Podman::new()
+}
+
+#[fixture]
+fn docker() -> Docker {
+    Docker::new()
+}
+
+fn alpine() -> Container {
+    let mut container = Container::from_image(Image::from_str("docker.io/library/alpine").unwrap());
+
+    container
+        .command(vec!["sleep".into(), "300".into()])
+        .wait_for(WaitStrategy::WaitTime {
+            duration: Duration::from_millis(500),
+        });
+
+    container
+}
+
+fn temp_dir(container: &Container) -> PathBuf {
+    let dir = std::env::temp_dir().join(&container.name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[rstest]
+#[case::podman_files_before_start(podman())]
+#[case::docker_files_before_start(docker())]
+fn test_files_before_start(#[case] client: impl Client) {
+    let mut container = alpine();
+    let dir = temp_dir(&container);
+
+    fs::write(dir.join("fixture.txt"), "from host").unwrap();
+
+    container
+        .copy_file(dir.join("fixture.txt"), "/fixtures/host.txt")
+        .copy_content("from memory", "/fixtures/memory.txt")
+        // the files have to exist when the command starts
+        .command(vec![
+            "sh".into(),
+            "-c".into(),
+            "cat /fixtures/host.txt /fixtures/memory.txt && sleep 300".into(),
+        ]);
+
+    let handle = client.create(container);
+
+    handle.run_and_wait().unwrap();
+
+    let output = handle
+        .exec(&Exec::new([
+            "cat",
+            "/fixtures/host.txt",
+            "/fixtures/memory.txt",
+        ]))
+        .unwrap();
+
+    assert_eq!(output.stdout, "from hostfrom memory");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[rstest]
+#[case::podman_copy(podman())]
+#[case::docker_copy(docker())]
+fn test_copy_to_and_from(#[case] client: impl Client) {
+    let container = alpine();
+    let dir = temp_dir(&container);
+    let handle = client.create(container);
+
+    fs::write(dir.join("in.txt"), "fixture").unwrap();
+
+    handle.run_and_wait().unwrap();
+    handle.copy_to(&dir.join("in.txt"), "/tmp/in.txt").unwrap();
+    handle
+        .exec(&Exec::new(["sh", "-c", "cat /tmp/in.txt > /tmp/out.txt"]))
+        .unwrap();
+    handle
+        .copy_from("/tmp/out.txt", &dir.join("out.txt"))
+        .unwrap();
+
+    assert_eq!(fs::read_to_string(dir.join("out.txt")).unwrap(), "fixture");
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,2125 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Read},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use base64::prelude::*;
+use common::{decode, log_frame, FakeEngine, FakeReaper, Reply, Request};
+use contain_rs_core::{
+    client::{
+        docker_api::DockerApi,
+        http::Endpoint,
+        reaper::{Reaper, REAPER_IMAGE},
+        CleanupPolicy, Client, EventFilter, Handle, Pruned,
+    },
+    container::{
+        Container, Exec, HealthCheck, HttpWait, Image, ImageBuild, LogStream, LogWait, Network,
+        PullPolicy, Resources, WaitStrategy,
+    },
+    error::ContainersError,
+    rt::{ContainerStatus, EventKind},
+    session::{host_id, session_id, HOST_LABEL, PID_LABEL, REAPER_LABEL, SESSION_LABEL},
+};
+use serde_json::{json, Value};
+
+#[derive(Default)]
+struct FakeDocker {
+    images: Vec<String>,
+    containers: HashMap<String, FakeContainer>,
+    removed: Vec<(String, i32)>,
+    networks: HashMap<String, Value>,
+    volumes: HashMap<String, Value>,
+    host_port: u16,
+    host_ip: String,
+    readiness_checks: u32,
+}
+
+#[derive(Default)]
+struct FakeContainer {
+    running: bool,
+    paused: bool,
+    exit_code: i32,
+    health_checks: u32,
+    labels: Value,
+    auto_remove: bool,
+}
+
+impl FakeDocker {
+    fn handle(&mut self, request: &Request) -> Reply {
+        let (path, query) = request.path.split_once('?').unwrap_or((&request.path, ""));
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+        if let Some(image) = path
+            .strip_prefix("/images/")
+            .and_then(|path| path.strip_suffix("/json"))
+            .map(decode)
+        {
+            let image = image.as_str();
+
+            // containers report the id of their image, which is looked up for its digest
+            return if image == "sha256:abc" || self.images.iter().any(|i| i == image) {
+                Reply::json(
+                    200,
+                    json!({
+                        "Id": "sha256:abc",
+                        "RepoDigests": ["docker.io/library/nginx@sha256:0f3e"]
+                    }),
+                )
+            } else {
+                Reply::json(404, json!({ "message": format!("No such image: {image}") }))
+            };
+        }
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["build"]) => {
+                let entries = archive_entries(&request.raw);
+
+                if !entries
+                    .iter()
+                    .any(|(path, _)| path.trim_start_matches("./") == "Dockerfile")
+                {
+                    return Reply::chunked(
+                        200,
+                        b"{\"error\":\"Cannot locate specified Dockerfile\"}\n".to_vec(),
+                    );
+                }
+
+                query
+                    .split('&')
+                    .filter_map(|param| param.strip_prefix("t="))
+                    .for_each(|tag| {
+                        self.images
+                            .push(tag.replace("%2F", "/").replace("%3A", ":"))
+                    });
+
+                Reply::chunked(
+                    200,
+                    b"{\"stream\":\"Step 1/2 : FROM alpine\\n\"}\n{\"stream\":\"\\n\"}\n{\"stream\":\"Successfully built abc\\n\"}\n".to_vec(),
+                )
+            }
+            ("POST", ["images", "create"]) if query.contains("fromImage=missing") => {
+                Reply::chunked(
+                    200,
+                    b"{\"status\":\"Pulling\"}\n{\"error\":\"manifest unknown\"}\n".to_vec(),
+                )
+            }
+            ("POST", ["images", "create"]) => {
+                self.images.push("docker.io/library/nginx:latest".into());
+                Reply::chunked(
+                    200,
+                    b"{\"status\":\"Pulling\"}\n{\"status\":\"Done\"}\n".to_vec(),
+                )
+            }
+            ("POST", ["containers", "create"]) => {
+                let body = request.body.as_ref().unwrap();
+                let image = body["Image"].as_str().unwrap();
+
+                if !self.images.iter().any(|i| i == image) {
+                    return Reply::json(404, json!({ "message": "No such image" }));
+                }
+
+                let name = query.strip_prefix("name=").unwrap().to_string();
+
+                if self.containers.contains_key(&name) {
+                    return Reply::json(
+                        409,
+                        json!({ "message": format!("Conflict. The container name \"/{name}\" is already in use by container \"abc\"") }),
+                    );
+                }
+
+                self.containers.insert(
+                    name.clone(),
+                    FakeContainer {
+                        labels: body["Labels"].clone(),
+                        auto_remove: body["HostConfig"]["AutoRemove"] == true,
+                        ..Default::default()
+                    },
+                );
+
+                Reply::json(201, json!({ "Id": name, "Warnings": [] }))
+            }
+            ("POST", ["containers", name, "start"]) => match self.containers.get_mut(*name) {
+                // containers named `exit-<code>-...` finish right after they started
+                Some(container) => {
+                    match name
+                        .strip_prefix("exit-")
+                        .and_then(|rest| rest.split_once('-'))
+                    {
+                        Some((code, _)) => container.exit_code = code.parse().unwrap(),
+                        None => container.running = true,
+                    }
+
+                    // a finished container that is removed automatically is gone right away
+                    if container.auto_remove && !container.running {
+                        let exit_code = container.exit_code;
+
+                        self.containers.remove(*name);
+                        self.removed.push((name.to_string(), exit_code));
+                    }
+
+                    Reply::empty(204)
+                }
+                None => Reply::json(404, json!({ "message": "No such container" })),
+            },
+            ("POST", ["containers", name, "stop"]) => match self.containers.get_mut(*name) {
+                Some(container) => {
+                    container.running = false;
+                    Reply::empty(204)
+                }
+                None => Reply::json(
+                    404,
+                    json!({ "message": format!("No such container: {name}") }),
+                ),
+            },
+            ("POST", ["containers", name, action @ ("pause" | "unpause" | "restart" | "kill")]) => {
+                let Some(container) = self.containers.get_mut(*name) else {
+                    return Reply::json(404, json!({ "message": "No such container" }));
+                };
+
+                match *action {
+                    "pause" => container.paused = true,
+                    "unpause" => container.paused = false,
+                    "restart" => container.running = true,
+                    _ if query == "signal=SIGKILL" => {
+                        container.running = false;
+                        container.exit_code = 137;
+                    }
+                    _ => {}
+                }
+
+                Reply::empty(204)
+            }
+            ("DELETE", ["containers", name]) => match self.containers.remove(*name) {
+                Some(_) => Reply::empty(204),
+                None => Reply::json(404, json!({ "message": "No such container" })),
+            },
+            ("GET", ["containers", name, "json"]) => match self.containers.get_mut(*name) {
+                Some(container) => {
+                    container.health_checks += 1;
+
+                    // `finishing-...` containers exit with 0 after they were inspected twice
+                    if name.starts_with("finishing") && container.health_checks > 2 {
+                        container.running = false;
+                    }
+
+                    // `evented-...` and `held-...` containers only report their health through events
+                    let status = if name.starts_with("unhealthy") {
+                        "unhealthy"
+                    } else if container.health_checks > 2
+                        && !name.starts_with("slow")
+                        && !name.starts_with("evented")
+                        && !name.starts_with("held")
+                    {
+                        "healthy"
+                    } else {
+                        "starting"
+                    };
+
+                    let host_ip = match self.host_ip.as_str() {
+                        "" => "0.0.0.0",
+                        host_ip => host_ip,
+                    };
+
+                    Reply::json(
+                        200,
+                        json!({
+                            "Id": name,
+                            "Image": "sha256:abc",
+                            "State": {
+                                "Running": container.running,
+                                "Paused": container.paused,
+                                "ExitCode": container.exit_code,
+                                "Health": { "Status": status }
+                            },
+                            "NetworkSettings": {
+                                "Ports": {
+                                    "80/tcp": [{ "HostIp": host_ip, "HostPort": self.host_port.to_string() }],
+                                    "8080/tcp": [{ "HostIp": host_ip, "HostPort": self.host_port.to_string() }]
+                                }
+                            }
+                        }),
+                    )
+                }
+                None => Reply::json(404, json!({ "message": "No such container" })),
+            },
+            // `migrating-...` containers print their progress on stdout only
+            ("GET", ["containers", name, "logs"]) if name.starts_with("migrating") => {
+                Reply::chunked(
+                    200,
+                    log_frame(1, "migrations running\nmigrations complete\nready\n"),
+                )
+            }
+            // the log of `held-...` containers never ends
+            ("GET", ["containers", name, "logs"]) if name.starts_with("held") => {
+                Reply::held_open(200, log_frame(1, "starting up\n"))
+            }
+            ("GET", ["containers", _, "logs"]) => {
+                let mut body = log_frame(1, "starting up\n");
+                body.extend(log_frame(2, "ready for start up\n"));
+                Reply::chunked(200, body)
+            }
+            ("POST", ["containers", _, "exec"])
+                if request.body.as_ref().unwrap()["Cmd"] == json!(["pg_isready"]) =>
+            {
+                Reply::json(201, json!({ "Id": "ready" }))
+            }
+            ("POST", ["containers", _, "exec"])
+                if request.body.as_ref().unwrap()["Cmd"] == json!(["hang"]) =>
+            {
+                Reply::json(201, json!({ "Id": "hanging" }))
+            }
+            // the output of the command never ends
+            ("POST", ["exec", "hanging", "start"]) => Reply::held_open(200, Vec::new()),
+            ("POST", ["containers", _, "exec"])
+                if request.body.as_ref().unwrap()["Cmd"] == json!(["stuck"]) =>
+            {
+                Reply::json(201, json!({ "Id": "stuck" }))
+            }
+            // the output ends, but the exec is never reported as finished
+            ("POST", ["exec", "stuck", "start"]) => Reply::raw(200, Vec::new()),
+            ("GET", ["exec", "stuck", "json"]) => {
+                Reply::json(200, json!({ "Running": true, "ExitCode": null }))
+            }
+            ("POST", ["exec", "ready", "start"]) => {
+                self.readiness_checks += 1;
+                Reply::raw(200, log_frame(1, "no response\n"))
+            }
+            ("GET", ["exec", "ready", "json"]) => {
+                let exit_code = if self.readiness_checks > 2 { 0 } else { 2 };
+                Reply::json(200, json!({ "Running": false, "ExitCode": exit_code }))
+            }
+            ("POST", ["containers", _, "exec"]) => {
+                let cmd = &request.body.as_ref().unwrap()["Cmd"];
+                assert_eq!(
+                    cmd,
+                    &json!(["sh", "-c", "echo hello; echo oops >&2; exit 3"])
+                );
+                Reply::json(201, json!({ "Id": "exec1" }))
+            }
+            ("POST", ["exec", "exec1", "start"]) => {
+                let mut body = log_frame(1, "hello\n");
+                body.extend(log_frame(2, "oops\n"));
+                Reply::raw(200, body)
+            }
+            ("GET", ["exec", "exec1", "json"]) => {
+                Reply::json(200, json!({ "Running": false, "ExitCode": 3 }))
+            }
+            // only the init directory of the image exists
+            ("HEAD", ["containers", _, "archive"])
+                if query == "path=%2Fdocker-entrypoint-initdb.d" =>
+            {
+                let stat = json!({ "name": "docker-entrypoint-initdb.d", "size": 4096, "mode": (1u32 << 31) | 0o755 });
+
+                Reply::empty(200).header(
+                    "X-Docker-Container-Path-Stat",
+                    &BASE64_STANDARD.encode(stat.to_string()),
+                )
+            }
+            ("HEAD", ["containers", _, "archive"]) => Reply::empty(404),
+            ("PUT", ["containers", _, "archive"]) => Reply::empty(200),
+            // a symlink to the parent of the destination, followed by a file below it
+            ("GET", ["containers", _, "archive"]) if query == "path=%2Fescape" => {
+                let mut builder = tar::Builder::new(Vec::new());
+
+                let mut dir = tar::Header::new_gnu();
+                dir.set_entry_type(tar::EntryType::Directory);
+                dir.set_mode(0o755);
+                dir.set_size(0);
+                dir.set_cksum();
+                builder
+                    .append_data(&mut dir, "escape/", std::io::empty())
+                    .unwrap();
+
+                let mut link = tar::Header::new_gnu();
+                link.set_entry_type(tar::EntryType::Symlink);
+                link.set_size(0);
+                builder
+                    .append_link(&mut link, "escape/link", "../outside")
+                    .unwrap();
+
+                let mut file = tar::Header::new_gnu();
+                file.set_size(5);
+                file.set_mode(0o644);
+                file.set_cksum();
+                builder
+                    .append_data(&mut file, "escape/link/pwned", "pwned".as_bytes())
+                    .unwrap();
+
+                Reply::raw(200, builder.into_inner().unwrap())
+            }
+            ("GET", ["containers", _, "archive"]) => {
+                let mut builder = tar::Builder::new(Vec::new());
+                let mut header = tar::Header::new_gnu();
+                header.set_size(6);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder
+                    .append_data(&mut header, "report.txt", "report".as_bytes())
+                    .unwrap();
+                Reply::raw(200, builder.into_inner().unwrap())
+            }
+            ("GET", ["containers", "json"]) => {
+                assert!(query.contains("all=true"));
+
+                let containers: Vec<Value> = self
+                    .containers
+                    .iter()
+                    .filter(|(_, container)| container.labels.get(PID_LABEL).is_some())
+                    .map(|(name, container)| {
+                        json!({ "Id": name, "Names": [format!("/{name}")], "Labels": container.labels })
+                    })
+                    .collect();
+
+                Reply::json(200, json!(containers))
+            }
+            ("POST", ["volumes", "create"]) => {
+                let body = request.body.as_ref().unwrap();
+                let name = body["Name"].as_str().unwrap();
+                self.volumes
+                    .insert(name.to_string(), body["Labels"].clone());
+                Reply::json(201, json!({ "Name": name }))
+            }
+            ("GET", ["volumes", name]) => match self.volumes.get(*name) {
+                Some(labels) => Reply::json(200, json!({ "Name": name, "Labels": labels })),
+                None => Reply::json(404, json!({ "message": "get data: no such volume" })),
+            },
+            ("POST", ["networks", "create"]) => {
+                let body = request.body.as_ref().unwrap();
+                let name = body["Name"].as_str().unwrap();
+                self.networks
+                    .insert(name.to_string(), body["Labels"].clone());
+                Reply::json(201, json!({ "Id": name }))
+            }
+            ("GET", ["networks"]) => {
+                let networks: Vec<Value> = self
+                    .networks
+                    .iter()
+                    .filter(|(_, labels)| labels.get(PID_LABEL).is_some())
+                    .map(|(name, labels)| json!({ "Name": name, "Id": name, "Labels": labels }))
+                    .collect();
+
+                Reply::json(200, json!(networks))
+            }
+            ("GET", ["networks", name]) => {
+                if self.networks.contains_key(*name) {
+                    Reply::json(200, json!({ "Name": name }))
+                } else {
+                    Reply::json(404, json!({ "message": "network not found" }))
+                }
+            }
+            ("DELETE", ["networks", name]) => {
+                self.networks.remove(*name);
+                Reply::empty(204)
+            }
+            ("GET", ["events"]) if query.contains("evented-nginx") => {
+                let events: Vec<String> = [
+                    ("start", json!({})),
+                    ("health_status: healthy", json!({})),
+                    ("die", json!({ "exitCode": "0" })),
+                    ("destroy", json!({})),
+                ]
+                .into_iter()
+                .map(|(action, mut attributes)| {
+                    attributes["name"] = json!("evented-nginx");
+                    json!({
+                        "Type": "container",
+                        "Action": action,
+                        "Actor": { "ID": "evented-nginx", "Attributes": attributes },
+                    })
+                    .to_string()
+                })
+                .collect();
+
+                Reply::chunked(200, format!("{}\n", events.join("\n")).into_bytes())
+            }
+            // the subscription stays open, like it does on a real engine
+            ("GET", ["events"]) if query.contains("held-nginx") => {
+                let event = json!({
+                    "Type": "container",
+                    "Action": "health_status: healthy",
+                    "Actor": { "ID": "held-nginx", "Attributes": { "name": "held-nginx" } },
+                });
+
+                Reply::held_open(200, format!("{event}\n").into_bytes())
+            }
+            // the events of removed containers are replayed, when they are asked for
+            ("GET", ["events"]) => {
+                let events: Vec<String> = self
+                    .removed
+                    .iter()
+                    .filter(|(name, _)| query.contains(&format!("%22{name}%22")))
+                    .flat_map(|(name, exit_code)| {
+                        [
+                            (
+                                "die",
+                                json!({ "name": name, "exitCode": exit_code.to_string() }),
+                            ),
+                            ("destroy", json!({ "name": name })),
+                        ]
+                        .map(|(action, attributes)| {
+                            json!({
+                                "Type": "container",
+                                "Action": action,
+                                "Actor": { "ID": name, "Attributes": attributes },
+                            })
+                            .to_string()
+                        })
+                    })
+                    .collect();
+
+                Reply::chunked(
+                    200,
+                    events
+                        .iter()
+                        .map(|e| format!("{e}\n"))
+                        .collect::<String>()
+                        .into_bytes(),
+                )
+            }
+            _ => Reply::json(500, json!({ "message": "unexpected request" })),
+        }
+    }
+}
+
+fn fake_docker() -> FakeEngine {
+    fake_docker_mapping_to(49153)
+}
+
+///
+/// A fake docker that maps port 80 of its containers to the given host port.
+///
+fn fake_docker_mapping_to(host_port: u16) -> FakeEngine {
+    fake_docker_with(FakeDocker {
+        host_port,
+        ..Default::default()
+    })
+}
+
+fn fake_docker_with(state: FakeDocker) -> FakeEngine {
+    let state = Arc::new(Mutex::new(state));
+
+    FakeEngine::start(move |request| state.lock().unwrap().handle(request))
+}
+
+fn client(engine: &FakeEngine) -> DockerApi {
+    DockerApi::from_endpoint(Endpoint::from_str(&engine.endpoint()).unwrap())
+}
+
+fn nginx() -> Container {
+    Container::from_image(Image::from_str("docker.io/library/nginx").unwrap())
+}
+
+#[test]
+fn test_run_pulls_missing_image() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+
+    client.run(&container).unwrap();
+
+    assert!(client.runs(&container).unwrap());
+
+    let name = &container.name;
+
+    assert_eq!(
+        engine.request_lines(),
+        vec![
+            "GET /images/docker.io%2Flibrary%2Fnginx%3Alatest/json".to_string(),
+            "POST /images/create?fromImage=docker.io%2Flibrary%2Fnginx&tag=latest".to_string(),
+            format!("POST /containers/create?name={name}"),
+            format!("POST /containers/{name}/start"),
+            format!("GET /containers/{name}/json"),
+            "GET /images/sha256%3Aabc/json".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_inspect_looks_up_image_digest() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+
+    client.run(&container).unwrap();
+
+    let info = client.inspect(&container).unwrap().unwrap();
+
+    assert_eq!(info.image_id, "sha256:abc");
+    assert_eq!(info.image_digest.as_deref(), Some("sha256:0f3e"));
+}
+
+#[test]
+fn test_command_talks_to_endpoint() {
+    let engine = fake_docker();
+    let command = client(&engine).command();
+
+    assert_eq!(command.get_program(), "docker");
+    assert_eq!(
+        command.get_args().collect::<Vec<_>>(),
+        vec!["--host", engine.endpoint().as_str()]
+    );
+}
+
+#[test]
+fn test_pull_policy() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.pull_policy(PullPolicy::Never);
+
+    assert!(matches!(
+        client.run(&container),
+        Err(ContainersError::ImageNotFound { .. })
+    ));
+
+    client.pull(&container.image).unwrap();
+
+    assert!(client.image_exists(&container.image).unwrap());
+
+    client.run(&container).unwrap();
+
+    container.pull_policy(PullPolicy::Always).name("always");
+    client.run(&container).unwrap();
+
+    let pulls = engine
+        .request_lines()
+        .into_iter()
+        .filter(|line| line.starts_with("POST /images/create"))
+        .count();
+
+    assert_eq!(pulls, 2);
+}
+
+#[test]
+fn test_image_exists_encodes_reference() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let image = Image::from_name_and_tag("localhost:5000/team/app", "1.0");
+
+    assert!(!client.image_exists(&image).unwrap());
+    assert_eq!(
+        engine.request_lines(),
+        vec!["GET /images/localhost%3A5000%2Fteam%2Fapp%3A1.0/json".to_string()]
+    );
+}
+
+#[test]
+fn test_pull_error() {
+    let engine = fake_docker();
+    let client = client(&engine);
+
+    let result = client.pull(&Image::from_str("missing").unwrap());
+
+    assert!(matches!(
+        result,
+        Err(ContainersError::ImageNotFound { message }) if message == "manifest unknown"
+    ));
+}
+
+fn build_context(files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("contain-rs-context-{}", nginx().name));
+
+    for (name, content) in files {
+        std::fs::create_dir_all(dir.join(name).parent().unwrap()).unwrap();
+        std::fs::write(dir.join(name), content).unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn test_build_image() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let context = build_context(&[("Dockerfile", "FROM alpine\nARG VERSION\n")]);
+    let mut output = Vec::new();
+
+    let image = client
+        .build_image(
+            &ImageBuild::from_path(&context)
+                .build_arg("VERSION", "1.0")
+                .target("runtime")
+                .tag(Image::from_str("localhost/api:test").unwrap()),
+            |line| output.push(line.to_string()),
+        )
+        .unwrap();
+
+    std::fs::remove_dir_all(context).unwrap();
+
+    assert_eq!(String::from(&image), "localhost/api:test");
+    assert_eq!(
+        output,
+        vec!["Step 1/2 : FROM alpine", "Successfully built abc"]
+    );
+    assert!(client.image_exists(&image).unwrap());
+
+    let build = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.path.starts_with("/build"))
+        .unwrap();
+
+    assert_eq!(
+        build.path,
+        "/build?t=localhost%2Fapi%3Atest&target=runtime&buildargs=%7B%22VERSION%22%3A%221.0%22%7D"
+    );
+}
+
+#[test]
+fn test_build_image_error() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let context = build_context(&[("docker/Containerfile", "FROM alpine\n")]);
+
+    let result = client.build_image(&ImageBuild::from_path(&context), |_| {});
+
+    std::fs::remove_dir_all(context).unwrap();
+
+    assert!(matches!(
+        result,
+        Err(ContainersError::ApiError { message, .. }) if message == "Cannot locate specified Dockerfile"
+    ));
+}
+
+#[test]
+fn test_create_body() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .map_port(8080, 80)
+        .env_var("KEY", "value")
+        .mount("/tmp", "/data")
+        .label("com.example.stack", "backend")
+        .health_check(HealthCheck::new("curl http://localhost || exit 1"));
+
+    client.run(&container).unwrap();
+
+    let body = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.path.starts_with("/containers/create"))
+        .and_then(|request| request.body)
+        .unwrap();
+
+    assert_eq!(body["Image"], "docker.io/library/nginx:latest");
+    assert_eq!(body["Env"], json!(["KEY=value"]));
+    assert_eq!(body["ExposedPorts"], json!({ "80/tcp": {} }));
+    assert_eq!(
+        body["HostConfig"]["PortBindings"],
+        json!({ "80/tcp": [{ "HostPort": "8080" }] })
+    );
+    assert_eq!(body["HostConfig"]["Binds"], json!(["/tmp:/data"]));
+    assert_eq!(body["Labels"]["com.example.stack"], "backend");
+    assert_eq!(body["Labels"][SESSION_LABEL], session_id());
+    assert_eq!(body["Labels"][PID_LABEL], std::process::id().to_string());
+    assert_eq!(
+        body["Healthcheck"]["Test"],
+        json!(["CMD-SHELL", "curl http://localhost || exit 1"])
+    );
+}
+
+#[test]
+fn test_named_volumes_carry_session_labels() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.volume("data", "/data");
+
+    client.run(&container).unwrap();
+    client.rm(&container).unwrap();
+    client.run(&container).unwrap();
+
+    let creates: Vec<Value> = engine
+        .requests()
+        .into_iter()
+        .filter(|request| request.path == "/volumes/create")
+        .filter_map(|request| request.body)
+        .collect();
+
+    // the volume exists by the second run and is not created again
+    assert_eq!(creates.len(), 1);
+    assert_eq!(creates[0]["Name"], "data");
+    assert_eq!(creates[0]["Labels"][SESSION_LABEL], session_id());
+    assert_eq!(
+        creates[0]["Labels"][PID_LABEL],
+        std::process::id().to_string()
+    );
+}
+
+#[test]
+fn test_create_body_resources() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.resources(
+        Resources::new()
+            .memory(64 * 1024 * 1024)
+            .memory_swap(-1)
+            .cpus(0.5)
+            .pids_limit(100)
+            .ulimit("nofile", 1024, 2048),
+    );
+
+    client.run(&container).unwrap();
+
+    let body = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.path.starts_with("/containers/create"))
+        .and_then(|request| request.body)
+        .unwrap();
+
+    let host_config = &body["HostConfig"];
+
+    assert_eq!(host_config["Memory"], 67108864);
+    assert_eq!(host_config["MemorySwap"], -1);
+    assert_eq!(host_config["NanoCpus"], 500000000);
+    assert_eq!(host_config["PidsLimit"], 100);
+    assert_eq!(
+        host_config["Ulimits"],
+        json!([{ "Name": "nofile", "Soft": 1024, "Hard": 2048 }])
+    );
+}
+
+#[test]
+fn test_expose_port() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.map_port(8443, 443).expose_port(80);
+
+    // handles can be used as trait objects
+    let handle: Box<dyn Handle> = Box::new(client.create(container));
+
+    handle.run().unwrap();
+
+    let body = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.path.starts_with("/containers/create"))
+        .and_then(|request| request.body)
+        .unwrap();
+
+    assert_eq!(
+        body["HostConfig"]["PortBindings"],
+        json!({ "443/tcp": [{ "HostPort": "8443" }], "80/tcp": [{ "HostPort": "" }] })
+    );
+    assert_eq!(handle.host_port(80.into()).unwrap(), Some(49153));
+    assert_eq!(handle.host_port(53.into()).unwrap(), None);
+}
+
+#[test]
+fn test_handle_lifecycle() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::HealthCheck);
+
+    let handle = client.create(container);
+
+    handle.run_and_wait().unwrap();
+
+    assert!(handle.is_running().unwrap());
+
+    handle.rm().unwrap();
+
+    assert!(!handle.exists().unwrap());
+}
+
+#[test]
+fn test_pause_restart_and_kill() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    // rounded up to 3 seconds
+    container.stop_timeout(Duration::from_millis(2500));
+
+    let handle = client.create(container.clone());
+
+    handle.run().unwrap();
+
+    handle.pause().unwrap();
+    assert!(client.inspect(&container).unwrap().unwrap().state.paused);
+
+    handle.unpause().unwrap();
+    assert!(!client.inspect(&container).unwrap().unwrap().state.paused);
+
+    handle.kill("SIGHUP").unwrap();
+    assert!(handle.is_running().unwrap());
+
+    handle.kill("SIGKILL").unwrap();
+
+    let state = client.inspect(&container).unwrap().unwrap().state;
+
+    assert!(!state.running);
+    assert_eq!(state.exit_code, 137);
+
+    handle.restart().unwrap();
+    assert!(handle.is_running().unwrap());
+
+    handle.stop().unwrap();
+
+    let name = &container.name;
+    let requests: Vec<String> = engine
+        .request_lines()
+        .into_iter()
+        .filter(|line| line.starts_with("POST"))
+        .skip(3)
+        .collect();
+
+    assert_eq!(
+        requests,
+        vec![
+            format!("POST /containers/{name}/pause"),
+            format!("POST /containers/{name}/unpause"),
+            format!("POST /containers/{name}/kill?signal=SIGHUP"),
+            format!("POST /containers/{name}/kill?signal=SIGKILL"),
+            format!("POST /containers/{name}/restart?t=3"),
+            format!("POST /containers/{name}/stop?t=3"),
+        ]
+    );
+}
+
+#[test]
+fn test_cleanup_policy_stop() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+
+    let handle = client
+        .create(container.clone())
+        .cleanup_policy(CleanupPolicy::Stop);
+
+    handle.run().unwrap();
+
+    drop(handle);
+
+    assert!(client.exists(&container).unwrap());
+    assert!(!client.runs(&container).unwrap());
+}
+
+#[test]
+fn test_cleanup_policy_keep_on_failure() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+
+    let handle = client
+        .create(container.clone())
+        .cleanup_policy(CleanupPolicy::KeepOnFailure);
+
+    handle.run().unwrap();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || {
+        let _handle = handle;
+        panic!("test failed");
+    }));
+
+    assert!(result.is_err());
+    assert!(client.runs(&container).unwrap());
+
+    let handle = client
+        .create(container.clone())
+        .cleanup_policy(CleanupPolicy::KeepOnFailure);
+
+    handle.cleanup().unwrap();
+
+    assert!(!client.exists(&container).unwrap());
+}
+
+#[test]
+fn test_cleanup_without_daemon() {
+    let engine = fake_docker();
+    let handle = client(&engine).create(nginx());
+
+    handle.run().unwrap();
+
+    drop(engine);
+
+    assert!(matches!(
+        handle.cleanup(),
+        Err(ContainersError::DaemonUnreachable { .. })
+    ));
+    // dropping the handle only logs the failure
+}
+
+#[test]
+fn test_wait_timeout() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("slow-nginx")
+        .wait_for(WaitStrategy::HealthCheck)
+        .startup_timeout(Duration::from_millis(500));
+
+    client.run(&container).unwrap();
+
+    let started = Instant::now();
+
+    match client.wait(&container) {
+        Err(ContainersError::WaitTimeout {
+            container_name,
+            timeout,
+            last_state,
+            last_log_lines,
+            ..
+        }) => {
+            assert_eq!(container_name, "slow-nginx");
+            assert_eq!(timeout, Duration::from_millis(500));
+            assert!(last_state.unwrap().running);
+            // stdout and stderr are read separately, so their lines may arrive in any order
+            let mut last_log_lines = last_log_lines;
+            last_log_lines.sort();
+            assert_eq!(last_log_lines, vec!["ready for start up", "starting up"]);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_events() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.name("evented-nginx");
+
+    let handle = client.create(container);
+
+    let kinds: Vec<EventKind> = handle
+        .events()
+        .unwrap()
+        .map(|event| event.unwrap().kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::Start,
+            EventKind::HealthStatus(ContainerStatus::Healthy),
+            EventKind::Die { exit_code: Some(0) },
+            EventKind::Destroy,
+        ]
+    );
+
+    client
+        .events(&EventFilter::new().label("stack", "backend"))
+        .unwrap()
+        .for_each(drop);
+
+    let requests = engine.request_lines();
+
+    assert_eq!(
+        requests[0],
+        "GET /events?filters=%7B%22container%22%3A%5B%22evented-nginx%22%5D%2C%22type%22%3A%5B%22container%22%5D%7D"
+    );
+    assert_eq!(
+        requests[1],
+        "GET /events?filters=%7B%22label%22%3A%5B%22stack%3Dbackend%22%5D%2C%22type%22%3A%5B%22container%22%5D%7D"
+    );
+}
+
+#[test]
+fn test_wait_for_health_check_events() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("evented-nginx")
+        .wait_for(WaitStrategy::HealthCheck)
+        .startup_timeout(Duration::from_secs(5));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    let requests = engine.request_lines();
+
+    assert!(requests
+        .iter()
+        .any(|request| request.starts_with("GET /events?filters=") && request.contains("&since=")));
+}
+
+#[test]
+fn test_wait_closes_events() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("held-nginx")
+        .wait_for(WaitStrategy::HealthCheck)
+        .startup_timeout(Duration::from_secs(5));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(2);
+
+    while engine.open_connections() > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(engine.open_connections(), 0);
+}
+
+#[test]
+fn test_log_streams() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+
+    client.run(&container).unwrap();
+
+    let mut log = client.log(&container).unwrap().unwrap();
+    let lines = |reader: &mut os_pipe::PipeReader| -> Vec<String> {
+        std::io::BufReader::new(reader)
+            .lines()
+            .map(Result::unwrap)
+            .collect()
+    };
+
+    let mut merged = lines(&mut log.reader);
+    merged.sort();
+
+    assert_eq!(merged, vec!["ready for start up", "starting up"]);
+    assert_eq!(lines(&mut log.stdout), vec!["starting up"]);
+    assert_eq!(lines(&mut log.stderr), vec!["ready for start up"]);
+}
+
+#[test]
+fn test_log_closes_connection() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.name("held-nginx");
+    client.run(&container).unwrap();
+
+    let mut log = client.log(&container).unwrap().unwrap();
+    let mut line = String::new();
+    std::io::BufReader::new(&mut log.stdout)
+        .read_line(&mut line)
+        .unwrap();
+
+    assert_eq!(line, "starting up\n");
+    assert_eq!(engine.open_connections(), 1);
+
+    drop(log);
+
+    let deadline = Instant::now() + Duration::from_secs(2);
+
+    while engine.open_connections() > 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(engine.open_connections(), 0);
+}
+
+#[test]
+fn test_wait_strategy_timeout() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(
+        WaitStrategy::WaitTime {
+            duration: Duration::from_secs(60),
+        }
+        .with_timeout(Duration::from_millis(100)),
+    );
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::WaitTimeout { timeout, .. }) if timeout == Duration::from_millis(100)
+    ));
+}
+
+#[test]
+fn test_wait_for_log() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::log_message(
+        regex::Regex::from_str("ready for start up").unwrap(),
+    ));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_wait_for_log_occurrences() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(
+        LogWait::new(regex::Regex::from_str("up$").unwrap())
+            .times(2)
+            .into(),
+    );
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    container.wait_for(
+        LogWait::new(regex::Regex::from_str("up$").unwrap())
+            .times(3)
+            .into(),
+    );
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::ContainerWaitFailed { .. })
+    ));
+}
+
+#[test]
+fn test_wait_for_log_stream() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(
+        LogWait::new(regex::Regex::from_str("ready").unwrap())
+            .stream(LogStream::Stderr)
+            .into(),
+    );
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    container.wait_for(
+        LogWait::new(regex::Regex::from_str("ready").unwrap())
+            .stream(LogStream::Stdout)
+            .into(),
+    );
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::ContainerWaitFailed { .. })
+    ));
+}
+
+#[test]
+fn test_wait_for_all() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::all([
+        WaitStrategy::HealthCheck,
+        WaitStrategy::log_message(regex::Regex::from_str("ready for start up").unwrap()),
+    ]));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_wait_for_any() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::any([
+        WaitStrategy::log_message(regex::Regex::from_str("never printed").unwrap()),
+        WaitStrategy::log_message(regex::Regex::from_str("ready for start up").unwrap()),
+    ]));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_wait_for_sequence() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    // consecutive lines satisfy consecutive strategies
+    container
+        .name("migrating-nginx")
+        .wait_for(WaitStrategy::sequence([
+            WaitStrategy::log_message(regex::Regex::from_str("migrations complete").unwrap()),
+            WaitStrategy::log_message(regex::Regex::from_str("ready").unwrap()),
+        ]));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    container.wait_for(WaitStrategy::sequence([
+        WaitStrategy::log_message(regex::Regex::from_str("ready").unwrap()),
+        WaitStrategy::log_message(regex::Regex::from_str("migrations complete").unwrap()),
+    ]));
+
+    match client.wait(&container) {
+        Err(ContainersError::ContainerWaitFailed {
+            wait_strategy: WaitStrategy::LogMessage { pattern, .. },
+            ..
+        }) => assert_eq!(pattern.as_str(), "migrations complete"),
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_wait_for_all_reports_failed_strategy() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("unhealthy-nginx")
+        .wait_for(WaitStrategy::all([
+            WaitStrategy::log_message(regex::Regex::from_str("ready for start up").unwrap()),
+            WaitStrategy::HealthCheck,
+        ]));
+
+    client.run(&container).unwrap();
+
+    match client.wait(&container) {
+        Err(ContainersError::WaitStrategyFailed {
+            wait_strategy: WaitStrategy::HealthCheck,
+            source,
+        }) => assert!(matches!(
+            *source,
+            ContainersError::ContainerStatusError {
+                status: ContainerStatus::Unhealthy
+            }
+        )),
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_wait_for_composite_timeout() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("slow-nginx")
+        .wait_for(WaitStrategy::all([
+            WaitStrategy::log_message(regex::Regex::from_str("ready for start up").unwrap()),
+            WaitStrategy::HealthCheck,
+        ]))
+        .startup_timeout(Duration::from_millis(500));
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::WaitTimeout {
+            wait_strategy: WaitStrategy::HealthCheck,
+            ..
+        })
+    ));
+
+    // every strategy that is still pending is reported
+    container.wait_for(WaitStrategy::any([
+        WaitStrategy::HealthCheck,
+        WaitStrategy::command(["hang"]),
+    ]));
+
+    match client.wait(&container) {
+        Err(ContainersError::WaitTimeout {
+            wait_strategy: WaitStrategy::Any { strategies },
+            ..
+        }) => assert_eq!(strategies.len(), 2),
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+fn health_endpoint(ready_after: usize) -> FakeEngine {
+    let polls = AtomicUsize::new(0);
+
+    FakeEngine::start_tcp(move |_| {
+        if polls.fetch_add(1, Ordering::SeqCst) < ready_after {
+            Reply::json(503, json!({ "status": "DOWN" }))
+        } else {
+            Reply::json(200, json!({ "status": "UP" }))
+        }
+    })
+}
+
+#[test]
+fn test_wait_for_http() {
+    let endpoint = health_endpoint(2);
+    let engine = fake_docker_mapping_to(endpoint.port());
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(
+        HttpWait::new(80)
+            .path("/health")
+            .body(regex::Regex::from_str("UP").unwrap())
+            .poll_interval(Duration::from_millis(10))
+            .into(),
+    );
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    assert_eq!(
+        endpoint.request_lines(),
+        vec!["GET /health", "GET /health", "GET /health"]
+    );
+}
+
+#[test]
+fn test_wait_for_http_status() {
+    let endpoint = health_endpoint(0);
+    let engine = fake_docker_mapping_to(endpoint.port());
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .wait_for(HttpWait::new(80).method("HEAD").status(204).into())
+        .startup_timeout(Duration::from_millis(500));
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::WaitTimeout {
+            wait_strategy: WaitStrategy::Http(_),
+            ..
+        })
+    ));
+    assert!(endpoint.request_lines().iter().all(|line| line == "HEAD /"));
+}
+
+#[test]
+fn test_wait_for_port_open() {
+    let listener = FakeEngine::start_tcp(|_| Reply::empty(204));
+    let engine = fake_docker_mapping_to(listener.port());
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::port_open(80));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_wait_on_published_host() {
+    // the published port is only reachable on the machine of the engine, not on localhost
+    let listener =
+        FakeEngine::start_tcp_on("127.0.0.2", |_| Reply::json(200, json!({ "status": "UP" })));
+    let strategy = WaitStrategy::all([
+        HttpWait::new(80)
+            .poll_interval(Duration::from_millis(10))
+            .into(),
+        WaitStrategy::port_open(80),
+    ]);
+
+    let state = Mutex::new(FakeDocker {
+        host_port: listener.port(),
+        ..Default::default()
+    });
+    let engine = FakeEngine::start_tcp_on("127.0.0.2", move |request| {
+        state.lock().unwrap().handle(request)
+    });
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .wait_for(strategy.clone())
+        .startup_timeout(Duration::from_secs(5));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    // ports bound to a specific ip are reached there
+    let engine = fake_docker_with(FakeDocker {
+        host_port: listener.port(),
+        host_ip: "127.0.0.2".into(),
+        ..Default::default()
+    });
+    let client = self::client(&engine);
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_wait_for_port_open_behind_proxy() {
+    // accepts connections like a proxy of the runtime, but closes them as nothing listens behind it
+    let proxy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = proxy.local_addr().unwrap().port();
+
+    std::thread::spawn(move || proxy.incoming().for_each(drop));
+
+    let engine = fake_docker_mapping_to(port);
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .wait_for(WaitStrategy::port_open(80))
+        .startup_timeout(Duration::from_millis(500));
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::WaitTimeout {
+            wait_strategy: WaitStrategy::PortOpen { .. },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_wait_for_all_ports_open() {
+    let closed_port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let engine = fake_docker_mapping_to(closed_port);
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .expose_port(80)
+        .wait_for(WaitStrategy::all_ports_open())
+        .startup_timeout(Duration::from_millis(500));
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::WaitTimeout {
+            wait_strategy: WaitStrategy::PortOpen { .. },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_wait_for_all_ports_open_without_ports() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::all_ports_open());
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::ContainerWaitFailed { .. })
+    ));
+}
+
+#[test]
+fn test_wait_for_command() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::command(["pg_isready"]));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    let attempts = engine
+        .request_lines()
+        .iter()
+        .filter(|line| *line == "POST /exec/ready/start")
+        .count();
+
+    assert_eq!(attempts, 3);
+}
+
+#[test]
+fn test_wait_for_hanging_command() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .wait_for(WaitStrategy::command(["hang"]))
+        .startup_timeout(Duration::from_millis(500));
+
+    client.run(&container).unwrap();
+
+    let started = Instant::now();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::WaitTimeout {
+            wait_strategy: WaitStrategy::Command { .. },
+            ..
+        })
+    ));
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn test_wait_detects_exit() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("exit-1-nginx")
+        .wait_for(WaitStrategy::log_message(
+            regex::Regex::from_str("never printed").unwrap(),
+        ));
+
+    client.run(&container).unwrap();
+
+    match client.wait(&container) {
+        Err(ContainersError::ContainerExited {
+            container_name,
+            exit_code,
+            last_log_lines,
+        }) => {
+            assert_eq!(container_name, "exit-1-nginx");
+            assert_eq!(exit_code, 1);
+            // stdout and stderr are read separately, so their lines may arrive in any order
+            let mut last_log_lines = last_log_lines;
+            last_log_lines.sort();
+            assert_eq!(last_log_lines, vec!["ready for start up", "starting up"]);
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_wait_detects_exit_during_health_check() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("exit-137-nginx")
+        .wait_for(WaitStrategy::HealthCheck);
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::ContainerExited { exit_code: 137, .. })
+    ));
+}
+
+#[test]
+fn test_wait_for_exit() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("exit-0-migrations")
+        .wait_for(WaitStrategy::Exit { expected_code: 0 });
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    container
+        .name("exit-2-migrations")
+        .wait_for(WaitStrategy::Exit { expected_code: 0 });
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::ContainerExited { exit_code: 2, .. })
+    ));
+}
+
+#[test]
+fn test_wait_detects_exit_of_removed_container() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("exit-1-nginx")
+        .auto_remove(true)
+        .wait_for(WaitStrategy::HealthCheck);
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.wait(&container),
+        Err(ContainersError::ContainerExited { exit_code: 1, .. })
+    ));
+
+    container
+        .name("exit-0-migrations")
+        .auto_remove(true)
+        .wait_for(WaitStrategy::Exit { expected_code: 0 });
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_wait_for_exit_after_running() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .name("finishing-migrations")
+        .wait_for(WaitStrategy::Exit { expected_code: 0 });
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    // the log ends before the exit is polled again
+    container
+        .name("finishing-seed")
+        .wait_for(WaitStrategy::any([
+            WaitStrategy::log_message(regex::Regex::from_str("never printed").unwrap()),
+            WaitStrategy::Exit { expected_code: 0 },
+        ]));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_wait_follows_log_only_for_log_messages() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::HealthCheck);
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+
+    assert!(!engine
+        .request_lines()
+        .iter()
+        .any(|line| line.contains("/logs")));
+
+    container.wait_for(WaitStrategy::all([
+        WaitStrategy::HealthCheck,
+        WaitStrategy::log_message(regex::Regex::from_str("ready for start up").unwrap()),
+    ]));
+
+    client.wait(&container).unwrap();
+
+    assert!(engine
+        .request_lines()
+        .iter()
+        .any(|line| line.contains("/logs")));
+}
+
+#[test]
+fn test_wait_time_exceeds_startup_timeout() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .wait_for(WaitStrategy::WaitTime {
+            duration: Duration::from_millis(300),
+        })
+        .startup_timeout(Duration::from_millis(100));
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_exec() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+    let exec = Exec::new(["sh", "-c", "echo hello; echo oops >&2; exit 3"]);
+
+    client.run(&container).unwrap();
+
+    let output = client.exec(&container, &exec).unwrap();
+
+    assert_eq!(output.stdout, "hello\n");
+    assert_eq!(output.stderr, "oops\n");
+    assert_eq!(output.exit_code, Some(3));
+
+    let mut stream = client.exec_stream(&container, &exec).unwrap();
+    let lines: Vec<String> = stream.stream().lines().map(Result::unwrap).collect();
+
+    assert_eq!(lines, vec!["hello", "oops"]);
+    assert_eq!(stream.wait().unwrap(), Some(3));
+}
+
+#[test]
+fn test_exec_never_finishing() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.exec(&container, &Exec::new(["stuck"])),
+        Err(ContainersError::IOError(e)) if e.kind() == std::io::ErrorKind::TimedOut
+    ));
+}
+
+fn archive_entries(archive: &[u8]) -> Vec<(String, String)> {
+    let mut archive = tar::Archive::new(archive);
+
+    archive
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            (entry.path().unwrap().display().to_string(), content)
+        })
+        .collect()
+}
+
+#[test]
+fn test_run_with_files() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.copy_content("key=value", "/etc/app/app.conf");
+
+    client.run(&container).unwrap();
+
+    let upload = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.method == "PUT")
+        .unwrap();
+
+    assert_eq!(
+        upload.path,
+        format!("/containers/{}/archive?path=%2F", container.name)
+    );
+    assert_eq!(
+        archive_entries(&upload.raw),
+        vec![("etc/app/app.conf".to_string(), "key=value".to_string())]
+    );
+
+    // files have to be in place before the container starts
+    let lines = engine.request_lines();
+    let upload_index = lines.iter().position(|l| l.starts_with("PUT")).unwrap();
+    let start_index = lines.iter().position(|l| l.ends_with("/start")).unwrap();
+
+    assert!(upload_index < start_index);
+}
+
+#[test]
+fn test_copy_from_refuses_symlink_escape() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+    let dir = std::env::temp_dir().join(format!("contain-rs-escape-{}", container.name));
+
+    std::fs::create_dir_all(dir.join("outside")).unwrap();
+
+    client.run(&container).unwrap();
+
+    assert!(client
+        .copy_from(&container, "/escape", &dir.join("copied"))
+        .is_err());
+    assert!(!dir.join("outside").join("pwned").exists());
+    assert!(std::fs::symlink_metadata(dir.join("copied").join("link")).is_ok());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_copy() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+    let dir = std::env::temp_dir().join(format!("contain-rs-copy-{}", container.name));
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("fixture.sql"), "select 1;").unwrap();
+
+    client.run(&container).unwrap();
+    client
+        .copy_to(
+            &container,
+            &dir.join("fixture.sql"),
+            "/docker-entrypoint-initdb.d/init.sql",
+        )
+        .unwrap();
+
+    let upload = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.method == "PUT")
+        .unwrap();
+
+    assert_eq!(
+        upload.path,
+        format!(
+            "/containers/{}/archive?path=%2Fdocker-entrypoint-initdb.d",
+            container.name
+        )
+    );
+    assert_eq!(
+        archive_entries(&upload.raw),
+        vec![("init.sql".to_string(), "select 1;".to_string())]
+    );
+
+    client
+        .copy_from(&container, "/tmp/report.txt", &dir.join("out.txt"))
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(dir.join("out.txt")).unwrap(),
+        "report"
+    );
+
+    // existing directories receive the copied path under its own name, like with `docker cp`
+    client
+        .copy_to(
+            &container,
+            &dir.join("fixture.sql"),
+            "/docker-entrypoint-initdb.d",
+        )
+        .unwrap();
+
+    let upload = engine
+        .requests()
+        .into_iter()
+        .rfind(|request| request.method == "PUT")
+        .unwrap();
+
+    assert_eq!(
+        upload.path,
+        format!(
+            "/containers/{}/archive?path=%2Fdocker-entrypoint-initdb.d",
+            container.name
+        )
+    );
+    assert_eq!(
+        archive_entries(&upload.raw),
+        vec![("fixture.sql".to_string(), "select 1;".to_string())]
+    );
+
+    client
+        .copy_from(&container, "/tmp/report.txt", &dir)
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(dir.join("report.txt")).unwrap(),
+        "report"
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_network_lifecycle() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let network = client.network(Network::new("backend"));
+
+    network.create().unwrap();
+
+    assert!(network.exists().unwrap());
+
+    network.rm().unwrap();
+
+    assert!(!network.exists().unwrap());
+}
+
+///
+/// The pid of a process that already finished.
+///
+fn dead_pid() -> String {
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    child.id().to_string()
+}
+
+#[test]
+fn test_prune_orphans() {
+    let dead_pid = dead_pid();
+    let orphan_labels =
+        json!({ SESSION_LABEL: "gone", PID_LABEL: dead_pid, HOST_LABEL: host_id() });
+    let alive_labels = json!({
+        SESSION_LABEL: "other",
+        PID_LABEL: std::process::id().to_string(),
+        HOST_LABEL: host_id(),
+    });
+    // the pid of a process in another container sharing the runtime says nothing about this host
+    let other_host_labels =
+        json!({ SESSION_LABEL: "ci-job", PID_LABEL: dead_pid, HOST_LABEL: "other-host" });
+
+    let mut state = FakeDocker::default();
+
+    for (name, labels) in [
+        ("orphan", orphan_labels.clone()),
+        ("other-session", alive_labels),
+        ("other-host", other_host_labels),
+        ("foreign", json!({ "com.example.stack": "backend" })),
+    ] {
+        state.containers.insert(
+            name.into(),
+            FakeContainer {
+                labels,
+                ..Default::default()
+            },
+        );
+    }
+
+    state
+        .networks
+        .insert("orphan-network".into(), orphan_labels);
+
+    let engine = fake_docker_with(state);
+    let client = client(&engine);
+
+    let network = client.network(Network::new("backend"));
+    network.create().unwrap();
+
+    let handle = client.create(nginx());
+    handle.run().unwrap();
+
+    let pruned = client.prune_orphans().unwrap();
+
+    assert_eq!(
+        pruned,
+        Pruned {
+            containers: vec!["orphan".into()],
+            networks: vec!["orphan-network".into()],
+        }
+    );
+    assert!(handle.exists().unwrap());
+    assert!(network.exists().unwrap());
+    assert!(engine
+        .request_lines()
+        .contains(&"DELETE /containers/orphan?force=true".to_string()));
+    assert!(!engine
+        .request_lines()
+        .iter()
+        .any(|line| line.starts_with("DELETE /containers/other-host")));
+    assert_eq!(client.prune_orphans().unwrap(), Pruned::default());
+}
+
+#[test]
+fn test_start_reaper() {
+    // the first connection is closed right away, the reaper is not listening yet
+    let fake_reaper = FakeReaper::start(&["", "ACK"]);
+
+    let engine = fake_docker_with(FakeDocker {
+        images: vec![REAPER_IMAGE.into()],
+        host_port: fake_reaper.port(),
+        ..Default::default()
+    });
+    let client = client(&engine);
+
+    let reaper = Reaper::start(&client, "/var/run/docker.sock").unwrap();
+
+    let body = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.path.starts_with("/containers/create"))
+        .and_then(|request| request.body)
+        .unwrap();
+
+    assert_eq!(body["Image"], REAPER_IMAGE);
+    assert_eq!(
+        body["HostConfig"]["Binds"],
+        json!(["/var/run/docker.sock:/var/run/docker.sock"])
+    );
+    assert_eq!(body["HostConfig"]["AutoRemove"], true);
+    assert_eq!(body["Labels"][REAPER_LABEL], "true");
+    assert!(body["Labels"].get(SESSION_LABEL).is_none());
+
+    drop(reaper);
+
+    assert_eq!(
+        fake_reaper.disconnected_filter(),
+        Some(format!("label={SESSION_LABEL}={}", session_id()))
+    );
+}
+
+#[test]
+fn test_start_reaper_per_runtime() {
+    let reaper_creations = |engine: &FakeEngine| {
+        engine
+            .requests()
+            .iter()
+            .filter(|request| {
+                request.path.starts_with("/containers/create")
+                    && request.body.as_ref().unwrap()["Image"] == REAPER_IMAGE
+            })
+            .count()
+    };
+
+    let fake_reapers = [FakeReaper::start(&["ACK"]), FakeReaper::start(&["ACK"])];
+    let engines = fake_reapers.each_ref().map(|fake_reaper| {
+        fake_docker_with(FakeDocker {
+            images: vec![REAPER_IMAGE.into()],
+            host_port: fake_reaper.port(),
+            ..Default::default()
+        })
+    });
+
+    for engine in &engines {
+        client(engine).start_reaper().unwrap();
+    }
+
+    // a reaper is started once per runtime
+    client(&engines[0]).start_reaper().unwrap();
+
+    assert_eq!(reaper_creations(&engines[0]), 1);
+    assert_eq!(reaper_creations(&engines[1]), 1);
+}
+
+#[test]
+fn test_api_error() {
+    let engine = fake_docker();
+    let client = client(&engine);
+    let container = nginx();
+
+    match client.stop(&container) {
+        Err(ContainersError::ContainerNotExists { container_name }) => {
+            assert_eq!(container_name, container.name)
+        }
+        other => panic!("Unexpected result {other:?}"),
+    }
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.run(&container),
+        Err(ContainersError::NameConflict { .. })
+    ));
+}
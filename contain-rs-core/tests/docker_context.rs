@@ -0,0 +1,32 @@
+#![cfg(unix)]
+
+use std::{env, fs, os::unix::fs::PermissionsExt};
+
+use contain_rs_core::client::{docker::Docker, Client};
+
+///
+/// The docker cli is replaced by a script reporting a context on a remote host,
+/// which is why this is the only test of its binary.
+///
+#[test]
+fn test_host_of_docker_context() {
+    let dir = env::temp_dir().join(format!("contain-rs-docker-context-{}", std::process::id()));
+    let docker = dir.join("docker");
+
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        &docker,
+        "#!/bin/sh\n[ \"$1 $2\" = \"context inspect\" ] && echo tcp://10.1.2.3:2376\n",
+    )
+    .unwrap();
+    fs::set_permissions(&docker, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let path = env::var("PATH").unwrap_or_default();
+
+    env::set_var("PATH", format!("{}:{path}", dir.display()));
+    env::remove_var("DOCKER_HOST");
+
+    assert_eq!(Docker::new().host(), "10.1.2.3");
+
+    fs::remove_dir_all(dir).unwrap();
+}
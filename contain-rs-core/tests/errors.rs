@@ -0,0 +1,191 @@
+#![cfg(unix)]
+
+use contain_rs_core::{
+    client::{
+        docker_api::DockerApi,
+        http::Endpoint,
+        shared::{self, run_and_wait_for_command},
+        Client,
+    },
+    container::{Container, Exec, Image},
+    error::ContainersError,
+};
+use rstest::*;
+
+use std::{process::Command, str::FromStr};
+
+fn failing_command(stderr: &str) -> Command {
+    exiting_command(125, stderr)
+}
+
+///
+/// Stands in for a runtime, the arguments of the actual command are ignored.
+///
+fn exiting_command(exit_code: i32, stderr: &str) -> Command {
+    let mut command = Command::new("sh");
+
+    command
+        .arg("-c")
+        .arg(format!("echo out; echo '{stderr}' >&2; exit {exit_code}"));
+
+    command
+}
+
+fn nginx() -> Container {
+    Container::from_image(Image::from_str("docker.io/library/nginx").unwrap())
+}
+
+#[test]
+fn test_command_error() {
+    let result = run_and_wait_for_command(&mut failing_command("Error: something broke"));
+
+    match result {
+        Err(ContainersError::CommandError {
+            command,
+            status,
+            stdout,
+            stderr,
+        }) => {
+            assert!(command.starts_with("sh -c echo out;"));
+            assert_eq!(status.code(), Some(125));
+            assert_eq!(stdout, "out");
+            assert_eq!(stderr, "Error: something broke");
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn test_runtime_not_found() {
+    let result = run_and_wait_for_command(&mut Command::new("contain-rs-missing-runtime"));
+
+    assert!(matches!(
+        result,
+        Err(ContainersError::RuntimeNotFound { binary }) if binary == "contain-rs-missing-runtime"
+    ));
+}
+
+#[test]
+fn test_invalid_utf8_output() {
+    let mut command = Command::new("sh");
+
+    command.arg("-c").arg("printf '\\377'");
+
+    assert!(matches!(
+        run_and_wait_for_command(&mut command),
+        Err(ContainersError::Utf8Error(_))
+    ));
+}
+
+#[rstest]
+#[case::docker_image_not_found(
+    "Error response from daemon: pull access denied for nope, repository does not exist"
+)]
+#[case::podman_image_not_found("Error: nope: image not known")]
+fn test_image_not_found(#[case] stderr: &str) {
+    assert!(matches!(
+        run_and_wait_for_command(&mut failing_command(stderr)),
+        Err(ContainersError::ImageNotFound { message }) if message == stderr
+    ));
+}
+
+#[rstest]
+#[case::docker_port_allocated(
+    "Error response from daemon: Bind for 0.0.0.0:8080 failed: port is already allocated"
+)]
+#[case::podman_port_allocated(
+    "Error: rootlessport listen tcp 0.0.0.0:8080: bind: address already in use"
+)]
+fn test_port_already_allocated(#[case] stderr: &str) {
+    assert!(matches!(
+        run_and_wait_for_command(&mut failing_command(stderr)),
+        Err(ContainersError::PortAlreadyAllocated { .. })
+    ));
+}
+
+#[rstest]
+#[case::docker_name_conflict("Error response from daemon: Conflict. The container name \"/db\" is already in use by container \"abc\"")]
+#[case::podman_name_conflict(
+    "Error: creating container storage: the container name \"db\" is already in use by abc"
+)]
+fn test_name_conflict(#[case] stderr: &str) {
+    assert!(matches!(
+        run_and_wait_for_command(&mut failing_command(stderr)),
+        Err(ContainersError::NameConflict { .. })
+    ));
+}
+
+#[rstest]
+#[case::docker_unreachable("Cannot connect to the Docker daemon at unix:///var/run/docker.sock. Is the docker daemon running?")]
+#[case::podman_unreachable("Error: unable to connect to Podman socket: connection refused")]
+fn test_daemon_unreachable(#[case] stderr: &str) {
+    assert!(matches!(
+        run_and_wait_for_command(&mut failing_command(stderr)),
+        Err(ContainersError::DaemonUnreachable { .. })
+    ));
+}
+
+#[rstest]
+#[case::docker_container_not_found("Error response from daemon: No such container: nope")]
+#[case::podman_container_not_found(
+    "Error: no container with name or ID \"nope\" found: no such container"
+)]
+fn test_container_not_found(#[case] stderr: &str) {
+    assert!(matches!(
+        shared::exec(&mut failing_command(stderr), &nginx(), &Exec::new(["ls"])),
+        Err(ContainersError::ContainerNotExists { container_name }) if container_name == "nope"
+    ));
+}
+
+#[test]
+fn test_api_daemon_unreachable() {
+    let client = DockerApi::from_endpoint(
+        Endpoint::from_str("unix:///nonexistent/contain-rs/docker.sock").unwrap(),
+    );
+    let container = nginx();
+
+    assert!(matches!(
+        client.inspect(&container),
+        Err(ContainersError::DaemonUnreachable { message }) if message.contains("docker.sock")
+    ));
+}
+
+#[rstest]
+#[case::docker_not_running(1, "Error response from daemon: container abc is not running")]
+#[case::podman_not_running(
+    125,
+    "Error: can only create exec sessions on running containers: container state improper"
+)]
+#[case::not_invocable(
+    126,
+    "OCI runtime exec failed: exec failed: unable to start container process: exec: \"/data\": permission denied: unknown"
+)]
+fn test_exec_runtime_failure(#[case] exit_code: i32, #[case] stderr: &str) {
+    let result = shared::exec(
+        &mut exiting_command(exit_code, stderr),
+        &nginx(),
+        &Exec::new(["ls"]),
+    );
+
+    assert!(matches!(
+        result,
+        Err(ContainersError::CommandError { status, .. }) if status.code() == Some(exit_code)
+    ));
+}
+
+#[rstest]
+#[case::failure(3)]
+#[case::runtime_exit_code(125)]
+#[case::not_executable_exit_code(126)]
+fn test_exec_command_failure(#[case] exit_code: i32) {
+    let output = shared::exec(
+        &mut exiting_command(exit_code, "oops"),
+        &nginx(),
+        &Exec::new(["ls"]),
+    )
+    .unwrap();
+
+    assert_eq!(output.exit_code, Some(exit_code));
+    assert_eq!(output.stdout, "out\n");
+    assert_eq!(output.stderr, "oops\n");
+}
@@ -0,0 +1,159 @@
+use std::{
+    process::Command,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use contain_rs_core::{
+    client::{
+        shared::{build_events_command, events},
+        EventFilter,
+    },
+    error::ContainersError,
+    rt::{ContainerStatus, Event, EventKind},
+};
+use rstest::rstest;
+
+const DOCKER_EVENTS: &str = include_str!("fixtures/docker_events.jsonl");
+const PODMAN_EVENTS: &str = include_str!("fixtures/podman_events.jsonl");
+
+fn parse(json: &str) -> Vec<Event> {
+    json.lines()
+        .filter_map(|line| Event::from_json(line).unwrap())
+        .collect()
+}
+
+fn fixture(name: &str) -> String {
+    format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[rstest]
+#[case::docker(DOCKER_EVENTS, "4f1c2b7a9e3d")]
+#[case::podman(PODMAN_EVENTS, "8d2e4f6a1b3c")]
+fn test_common_fields(#[case] json: &str, #[case] container_id: &str) {
+    let events = parse(json);
+
+    assert!(events
+        .iter()
+        .all(|event| event.container_id == container_id));
+    assert!(events
+        .iter()
+        .all(|event| event.container_name == "contain-rs-postgres"));
+    assert_eq!(events[0].kind, EventKind::Create);
+    assert_eq!(events[0].attributes["contain-rs.session"], "3f9a1c");
+    assert_eq!(events[1].kind, EventKind::Start);
+}
+
+#[test]
+fn test_docker_events() {
+    let kinds: Vec<EventKind> = parse(DOCKER_EVENTS)
+        .into_iter()
+        .map(|event| event.kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::Create,
+            EventKind::Start,
+            EventKind::Other("exec_start".into()),
+            EventKind::HealthStatus(ContainerStatus::Healthy),
+            EventKind::Oom,
+            EventKind::Die {
+                exit_code: Some(137)
+            },
+            EventKind::Destroy,
+        ]
+    );
+}
+
+#[test]
+fn test_podman_events() {
+    let kinds: Vec<EventKind> = parse(PODMAN_EVENTS)
+        .into_iter()
+        .map(|event| event.kind)
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            EventKind::Create,
+            EventKind::Start,
+            EventKind::HealthStatus(ContainerStatus::Healthy),
+            EventKind::Die {
+                exit_code: Some(137)
+            },
+            EventKind::Other("cleanup".into()),
+            EventKind::Destroy,
+        ]
+    );
+}
+
+#[test]
+fn test_events_command() {
+    let filter = EventFilter::new()
+        .container("postgres")
+        .label("contain-rs.session", "3f9a1c")
+        .since(UNIX_EPOCH + Duration::from_secs(1710236488));
+
+    let mut command = Command::new("docker");
+    build_events_command(&mut command, &filter);
+
+    let args: Vec<_> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    assert_eq!(
+        args,
+        vec![
+            "events",
+            "--format",
+            "{{json .}}",
+            "--filter",
+            "type=container",
+            "--filter",
+            "container=postgres",
+            "--filter",
+            "label=contain-rs.session=3f9a1c",
+            "--since",
+            "1710236488",
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_events() {
+    let mut command = Command::new("sh");
+
+    // the events arguments end up as positional parameters of the script
+    command
+        .arg("-c")
+        .arg(format!("cat {}", fixture("podman_events.jsonl")));
+
+    let events: Vec<Event> = events(command, &EventFilter::new())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(events.len(), 6);
+    assert_eq!(events[5].kind, EventKind::Destroy);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cli_events_error() {
+    let mut command = Command::new("sh");
+
+    command
+        .arg("-c")
+        .arg("echo 'Cannot connect to the Docker daemon' >&2; exit 1");
+
+    let mut events = events(command, &EventFilter::new()).unwrap();
+
+    assert!(matches!(
+        events.next(),
+        Some(Err(ContainersError::DaemonUnreachable { .. }))
+    ));
+    assert!(events.next().is_none());
+}
@@ -0,0 +1,77 @@
+use contain_rs_core::{
+    client::{docker::Docker, podman::Podman, Client, Handle},
+    container::{Container, Exec, Image, WaitStrategy},
+};
+use rstest::*;
+
+use std::{io::BufRead, str::FromStr, time::Duration};
+
+#[fixture]
+fn podman() -> Podman {
+    Podman::new()
+}
+
+#[fixture]
+fn docker() -> Docker {
+    Docker::new()
+}
+
+fn alpine() -> Container {
+    let mut container = Container::from_image(Image::from_str("docker.io/library/alpine").unwrap());
+
+    container
+        .command(vec!["sleep".into(), "300".into()])
+        .wait_for(WaitStrategy::WaitTime {
+            duration: Duration::from_millis(500),
+        });
+
+    container
+}
+
+#[rstest]
+#[case::podman_exec(podman())]
+#[case::docker_exec(docker())]
+fn test_exec(#[case] client: impl Client) {
+    let handle = client.create(alpine());
+
+    handle.run_and_wait().unwrap();
+
+    let output = handle
+        .exec(
+            &Exec::new([
+                "sh",
+                "-c",
+                "echo $GREETING $(pwd) $(whoami); echo oops >&2; exit 3",
+            ])
+            .env_var("GREETING", "hello")
+            .workdir("/tmp")
+            .user("nobody"),
+        )
+        .unwrap();
+
+    assert_eq!(output.stdout, "hello /tmp nobody\n");
+    assert_eq!(output.stderr, "oops\n");
+    assert_eq!(output.exit_code, Some(3));
+}
+
+#[rstest]
+#[case::podman_exec_stream(podman())]
+#[case::docker_exec_stream(docker())]
+fn test_exec_stream(#[case] client: impl Client) {
+    let handle = client.create(alpine());
+
+    handle.run_and_wait().unwrap();
+
+    let mut stream = handle
+        .exec_stream(&Exec::new([
+            "sh",
+            "-c",
+            "for i in 1 2 3; do echo $i; sleep 1; done",
+        ]))
+        .unwrap();
+
+    let lines: Vec<String> = stream.stream().lines().map(Result::unwrap).collect();
+
+    assert_eq!(lines, vec!["1", "2", "3"]);
+    assert_eq!(stream.wait().unwrap(), Some(0));
+}
@@ -0,0 +1,108 @@
+use contain_rs_core::rt::{ContainerStatus, DetailedContainerInfo, ImageInfo};
+use rstest::rstest;
+
+const DOCKER_INSPECT: &str = include_str!("fixtures/docker_inspect.json");
+const DOCKER_IMAGE_INSPECT: &str = include_str!("fixtures/docker_image_inspect.json");
+const PODMAN_INSPECT: &str = include_str!("fixtures/podman_inspect.json");
+
+fn parse(json: &str) -> DetailedContainerInfo {
+    let mut infos: Vec<DetailedContainerInfo> = serde_json::from_str(json).unwrap();
+
+    assert_eq!(infos.len(), 1);
+
+    infos.remove(0)
+}
+
+#[rstest]
+#[case::docker(DOCKER_INSPECT, 49154)]
+#[case::podman(PODMAN_INSPECT, 40123)]
+fn test_common_fields(#[case] json: &str, #[case] host_port: u16) {
+    let info = parse(json);
+
+    assert!(info.name.ends_with("contain-rs-postgres"));
+    assert_eq!(info.config.image, "docker.io/library/postgres:16");
+    assert!(info
+        .config
+        .env
+        .contains(&"POSTGRES_PASSWORD=secret".to_string()));
+    assert_eq!(info.host_port(5432), Some(host_port));
+    assert_eq!(info.mounts[0].kind, "volume");
+    assert_eq!(info.mounts[0].name, "pgdata");
+    assert_eq!(info.mounts[0].destination, "/var/lib/postgresql/data");
+    assert!(info.mounts[0].rw);
+}
+
+#[test]
+fn test_docker_inspect() {
+    let info = parse(DOCKER_INSPECT);
+
+    assert_eq!(info.state.status, "exited");
+    assert!(!info.state.running);
+    assert!(info.state.oom_killed);
+    assert_eq!(info.state.exit_code, 137);
+    assert_eq!(info.state.started_at, "2024-03-12T09:41:28.001122334Z");
+    assert_eq!(info.state.finished_at, "2024-03-12T09:43:02.998877665Z");
+    assert_eq!(
+        info.state.health.as_ref().unwrap().status,
+        ContainerStatus::Unhealthy
+    );
+    assert_eq!(info.restart_count, 2);
+    assert!(info.image_id.starts_with("sha256:b2e1c8d6"));
+    // docker reports the digest with the image only
+    assert_eq!(info.image_digest, None);
+    assert_eq!(info.labels()["contain-rs.session"], "3f9a1c");
+    assert_eq!(info.ip_address("bridge"), Some("172.17.0.3"));
+    assert_eq!(info.ip_address("backend"), Some("172.20.0.2"));
+    assert_eq!(info.network_settings.networks["backend"].aliases[0], "db");
+    assert!(info.network_settings.networks["bridge"].aliases.is_empty());
+    assert_eq!(info.mounts[1].kind, "bind");
+    assert_eq!(info.mounts[1].source, "/home/user/init.sql");
+    assert!(!info.mounts[1].rw);
+}
+
+#[test]
+fn test_docker_image_inspect() {
+    let container = parse(DOCKER_INSPECT);
+    let images: Vec<ImageInfo> = serde_json::from_str(DOCKER_IMAGE_INSPECT).unwrap();
+
+    assert_eq!(images[0].id, container.image_id);
+    assert_eq!(
+        images[0].digest(),
+        Some("sha256:4aea012537edfad80f98d870a36e6b90b4c09b27be7f4b4759d72db863baeebb")
+    );
+}
+
+#[test]
+fn test_podman_inspect() {
+    let info = parse(PODMAN_INSPECT);
+
+    assert_eq!(info.state.status, "running");
+    assert!(info.state.running);
+    assert!(!info.state.oom_killed);
+    assert_eq!(info.state.exit_code, 0);
+    assert_eq!(info.state.started_at, "2024-03-12T10:02:11.876543210+01:00");
+    assert_eq!(info.state.finished_at, "0001-01-01T00:00:00Z");
+    assert_eq!(
+        info.state.health.as_ref().unwrap().status,
+        ContainerStatus::Healthy
+    );
+    assert_eq!(info.restart_count, 0);
+    assert_eq!(
+        info.image_digest.as_deref(),
+        Some("sha256:7d3e9a1b2c4f6e8d0a2c4e6f8b0d2f4a6c8e0b2d4f6a8c0e2b4d6f8a0c2e4b6d")
+    );
+    assert!(info.labels().is_empty());
+    assert_eq!(info.ip_address("podman"), Some("10.88.0.7"));
+    assert_eq!(info.ip_address("bridge"), None);
+}
+
+#[test]
+fn test_minimal_inspect() {
+    let info: DetailedContainerInfo =
+        serde_json::from_str(r#"{ "Id": "abc", "State": { "Running": true } }"#).unwrap();
+
+    assert!(info.state.running);
+    assert!(info.mounts.is_empty());
+    assert!(info.labels().is_empty());
+    assert_eq!(info.restart_count, 0);
+}
@@ -0,0 +1,72 @@
+use contain_rs_core::{
+    client::{docker::Docker, podman::Podman, Client, Handle},
+    container::{Container, Image, Network, WaitStrategy},
+};
+use rstest::*;
+
+use std::str::FromStr;
+
+#[fixture]
+fn podman() -> Podman {
+    Podman::new()
+}
+
+#[fixture]
+fn docker() -> Docker {
+    Docker::new()
+}
+
+#[rstest]
+#[case::podman_network_lifecycle(podman())]
+#[case::docker_network_lifecycle(docker())]
+fn test_network_lifecycle(#[case] client: impl Client) {
+    let network = client.network(Network::default().label("test", "network"));
+
+    assert!(!network.exists().unwrap());
+
+    network.create().unwrap();
+
+    assert!(network.exists().unwrap());
+
+    network.rm().unwrap();
+
+    assert!(!network.exists().unwrap());
+}
+
+#[rstest]
+#[case::podman_network_alias(podman())]
+#[case::docker_network_alias(docker())]
+fn test_network_alias(#[case] client: impl Client) {
+    let network = client.network(Network::default());
+
+    network.create().unwrap();
+
+    let mut server = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+
+    server.network(network.network()).network_alias("web");
+
+    let server = client.create(server);
+
+    server.run().unwrap();
+
+    let mut probe = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+
+    probe
+        .network(network.network())
+        .command(vec![
+            "sh".into(),
+            "-c".into(),
+            "until curl -sf http://web; do sleep 1; done; echo reachable; sleep 60".into(),
+        ])
+        .wait_for(WaitStrategy::LogMessage {
+            pattern: regex::Regex::from_str("reachable").unwrap(),
+        });
+
+    let probe = client.create(probe);
+
+    probe.run_and_wait().unwrap();
+
+    // containers have to be gone before their network can be removed
+    probe.rm().unwrap();
+    server.rm().unwrap();
+}
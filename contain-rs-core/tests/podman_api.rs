@@ -0,0 +1,466 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::{decode, log_frame, FakeEngine, Reply, Request};
+use contain_rs_core::{
+    client::{http::Endpoint, podman_api::PodmanApi, Client, Handle},
+    container::{Container, Exec, HealthCheck, Image, Network, Resources, WaitStrategy},
+    error::ContainersError,
+    session::{session_id, SESSION_LABEL},
+};
+use serde_json::{json, Value};
+
+#[derive(Default)]
+struct FakeLibpod {
+    images: Vec<String>,
+    containers: HashMap<String, bool>,
+    networks: Vec<String>,
+    volumes: Vec<String>,
+    health_checks: u32,
+}
+
+impl FakeLibpod {
+    fn handle(&mut self, request: &Request) -> Reply {
+        let path = request.path.split('?').next().unwrap();
+        let segments: Vec<&str> = path
+            .strip_prefix("/v4.0.0/libpod/")
+            .expect("libpod api prefix")
+            .split('/')
+            .collect();
+
+        if let Some(image) = path
+            .strip_prefix("/v4.0.0/libpod/images/")
+            .and_then(|path| path.strip_suffix("/json"))
+            .map(decode)
+        {
+            let image = image.as_str();
+
+            return if self.images.iter().any(|i| i == image) {
+                Reply::json(200, json!({ "Id": "abc" }))
+            } else {
+                Reply::json(
+                    404,
+                    libpod_error("image not known", &format!("{image}: image not known"), 404),
+                )
+            };
+        }
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("POST", ["images", "pull"]) => {
+                self.images.push("docker.io/library/nginx:latest".into());
+                Reply::chunked(
+                    200,
+                    b"{\"stream\":\"Pulling\"}\n{\"id\":\"abc\"}\n".to_vec(),
+                )
+            }
+            ("POST", ["containers", "create"]) => {
+                let spec = request.body.as_ref().unwrap();
+                let image = spec["image"].as_str().unwrap();
+
+                if !self.images.iter().any(|i| i == image) {
+                    return Reply::json(
+                        404,
+                        libpod_error("image not known", &format!("{image}: image not known"), 404),
+                    );
+                }
+
+                let name = spec["name"].as_str().unwrap();
+
+                if self.containers.contains_key(name) {
+                    return Reply::json(
+                        500,
+                        libpod_error(
+                            "that name is already in use",
+                            &format!("creating container storage: the container name \"{name}\" is already in use by abc: that name is already in use"),
+                            500,
+                        ),
+                    );
+                }
+
+                self.containers.insert(name.to_string(), false);
+
+                Reply::json(201, json!({ "Id": "abc", "Warnings": [] }))
+            }
+            ("POST", ["containers", name, "start"]) => {
+                self.containers.insert(name.to_string(), true);
+                Reply::empty(204)
+            }
+            ("POST", ["containers", name, "stop"]) => {
+                self.containers.insert(name.to_string(), false);
+                Reply::empty(204)
+            }
+            ("POST", ["containers", name, "pause" | "unpause" | "restart" | "kill"])
+                if !self.containers.contains_key(*name) =>
+            {
+                Reply::json(
+                    404,
+                    libpod_error(
+                        "no such container",
+                        &format!("no container with name or ID \"{name}\" found: no such container"),
+                        404,
+                    ),
+                )
+            }
+            ("POST", ["containers", _, "pause" | "unpause" | "restart" | "kill"]) => {
+                Reply::empty(204)
+            }
+            ("DELETE", ["containers", name]) => {
+                self.containers.remove(*name);
+                Reply::empty(200)
+            }
+            ("GET", ["containers", name, "json"]) => match self.containers.get(*name) {
+                Some(running) => {
+                    self.health_checks += 1;
+
+                    let status = if self.health_checks > 2 {
+                        "healthy"
+                    } else {
+                        "starting"
+                    };
+
+                    Reply::json(
+                        200,
+                        json!({
+                            "Id": "abc",
+                            "State": { "Running": running, "Healthcheck": { "Status": status } }
+                        }),
+                    )
+                }
+                None => Reply::json(
+                    404,
+                    libpod_error(
+                        "no such container",
+                        &format!("no container with name or ID \"{name}\" found: no such container"),
+                        404,
+                    ),
+                ),
+            },
+            ("GET", ["containers", _, "logs"]) => {
+                Reply::chunked(200, log_frame(1, "ready for start up\n"))
+            }
+            ("POST", ["volumes", "create"]) => {
+                let name = request.body.as_ref().unwrap()["Name"].as_str().unwrap();
+                self.volumes.push(name.to_string());
+                Reply::json(201, json!({ "Name": name }))
+            }
+            ("GET", ["volumes", name, "json"]) => {
+                if self.volumes.iter().any(|v| v == name) {
+                    Reply::json(200, json!({ "Name": name }))
+                } else {
+                    Reply::json(
+                        404,
+                        libpod_error("no such volume", &format!("{name}: no such volume"), 404),
+                    )
+                }
+            }
+            ("POST", ["networks", "create"]) => {
+                let name = request.body.as_ref().unwrap()["name"].as_str().unwrap();
+                self.networks.push(name.to_string());
+                Reply::json(200, json!({ "name": name }))
+            }
+            ("GET", ["networks", name, "json"]) => {
+                if self.networks.iter().any(|n| n == name) {
+                    Reply::json(200, json!({ "name": name }))
+                } else {
+                    Reply::json(
+                        404,
+                        libpod_error(
+                            "network not found",
+                            &format!("unable to find network with name or ID {name}: network not found"),
+                            404,
+                        ),
+                    )
+                }
+            }
+            ("DELETE", ["networks", name]) => {
+                self.networks.retain(|n| n != name);
+                Reply::json(200, json!([]))
+            }
+            ("POST", ["containers", name, "exec"]) => Reply::json(
+                409,
+                libpod_error(
+                    "container state improper",
+                    &format!("can only create exec sessions on running containers: container {name} is not running: container state improper"),
+                    409,
+                ),
+            ),
+            _ => Reply::json(500, libpod_error("unexpected request", "unexpected request", 500)),
+        }
+    }
+}
+
+///
+/// The error body libpod responds with, the `cause` is the last part of the `message`.
+///
+fn libpod_error(cause: &str, message: &str, response: u16) -> Value {
+    json!({ "cause": cause, "message": message, "response": response })
+}
+
+fn fake_libpod() -> FakeEngine {
+    let state = Arc::new(Mutex::new(FakeLibpod::default()));
+
+    FakeEngine::start(move |request| state.lock().unwrap().handle(request))
+}
+
+fn client(engine: &FakeEngine) -> PodmanApi {
+    PodmanApi::from_endpoint(Endpoint::from_str(&engine.endpoint()).unwrap())
+}
+
+fn nginx() -> Container {
+    Container::from_image(Image::from_str("docker.io/library/nginx").unwrap())
+}
+
+#[test]
+fn test_run_pulls_missing_image() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let container = nginx();
+
+    client.run(&container).unwrap();
+
+    let name = &container.name;
+
+    assert_eq!(
+        engine.request_lines(),
+        vec![
+            "GET /v4.0.0/libpod/images/docker.io%2Flibrary%2Fnginx%3Alatest/json".to_string(),
+            "POST /v4.0.0/libpod/images/pull?reference=docker.io%2Flibrary%2Fnginx%3Alatest"
+                .to_string(),
+            "POST /v4.0.0/libpod/containers/create".to_string(),
+            format!("POST /v4.0.0/libpod/containers/{name}/start"),
+        ]
+    );
+}
+
+#[test]
+fn test_api_error() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let container = nginx();
+
+    let error = client.exec(&container, &Exec::new(["true"])).unwrap_err();
+
+    match error {
+        ContainersError::ApiError { status, message } => {
+            assert_eq!(status, 409);
+            assert_eq!(
+                message,
+                format!("can only create exec sessions on running containers: container {} is not running: container state improper", container.name)
+            );
+        }
+        error => panic!("Unexpected error {error:?}"),
+    }
+}
+
+#[test]
+fn test_name_conflict() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let container = nginx();
+
+    client.run(&container).unwrap();
+
+    assert!(matches!(
+        client.run(&container),
+        Err(ContainersError::NameConflict { .. })
+    ));
+}
+
+#[test]
+fn test_container_not_found() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let container = nginx();
+
+    match client.pause(&container) {
+        Err(ContainersError::ContainerNotExists { container_name }) => {
+            assert_eq!(container_name, container.name)
+        }
+        other => panic!("Unexpected result {other:?}"),
+    }
+}
+
+#[test]
+fn test_invalid_port() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.map_port("http", 80);
+
+    assert!(matches!(
+        client.run(&container),
+        Err(ContainersError::InvalidPort { port }) if port == "http"
+    ));
+    assert!(!engine
+        .request_lines()
+        .iter()
+        .any(|line| line.ends_with("/containers/create")));
+}
+
+#[test]
+fn test_spec() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container
+        .map_port(8080, 80)
+        .expose_port("53/udp")
+        .env_var("KEY", "value")
+        .label("com.example.stack", "backend")
+        .volume("data", "/data")
+        .health_check(HealthCheck::new("curl http://localhost || exit 1"))
+        .resources(
+            Resources::new()
+                .memory(64 * 1024 * 1024)
+                .cpus(0.5)
+                .pids_limit(100)
+                .ulimit("nofile", 1024, 2048),
+        );
+
+    client.run(&container).unwrap();
+
+    let spec = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.path.ends_with("/containers/create"))
+        .and_then(|request| request.body)
+        .unwrap();
+
+    assert_eq!(spec["name"], json!(container.name));
+    assert_eq!(spec["env"], json!({ "KEY": "value" }));
+    assert_eq!(spec["labels"]["com.example.stack"], "backend");
+    assert_eq!(spec["labels"][SESSION_LABEL], session_id());
+    assert_eq!(
+        spec["portmappings"],
+        json!([
+            { "host_port": 8080, "container_port": 80, "protocol": "tcp" },
+            { "host_port": 0, "container_port": 53, "protocol": "udp" },
+        ])
+    );
+    assert_eq!(
+        spec["volumes"],
+        json!([{ "Name": "data", "Dest": "/data" }])
+    );
+    assert_eq!(
+        spec["healthconfig"]["Test"],
+        json!(["CMD-SHELL", "curl http://localhost || exit 1"])
+    );
+    assert_eq!(
+        spec["resource_limits"],
+        json!({
+            "memory": { "limit": 67108864, "swap": null },
+            "cpu": { "quota": 50000, "period": 100000 },
+            "pids": { "limit": 100 },
+        })
+    );
+    assert_eq!(
+        spec["r_limits"],
+        json!([{ "type": "RLIMIT_NOFILE", "soft": 1024, "hard": 2048 }])
+    );
+
+    let volume = engine
+        .requests()
+        .into_iter()
+        .find(|request| request.path.ends_with("/volumes/create"))
+        .and_then(|request| request.body)
+        .unwrap();
+
+    assert_eq!(volume["Name"], "data");
+    assert_eq!(volume["Label"][SESSION_LABEL], session_id());
+}
+
+#[test]
+fn test_handle_lifecycle() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::HealthCheck);
+
+    let handle = client.create(container);
+
+    handle.run_and_wait().unwrap();
+
+    assert!(handle.is_running().unwrap());
+
+    handle.rm().unwrap();
+
+    assert!(!handle.exists().unwrap());
+}
+
+#[test]
+fn test_pause_restart_and_kill() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    // rounded up to 3 seconds
+    container.stop_timeout(Duration::from_millis(2500));
+
+    let handle = client.create(container.clone());
+
+    handle.run().unwrap();
+    handle.pause().unwrap();
+    handle.unpause().unwrap();
+    handle.kill("SIGHUP").unwrap();
+    handle.restart().unwrap();
+    handle.stop().unwrap();
+
+    let name = &container.name;
+    let requests: Vec<String> = engine
+        .request_lines()
+        .into_iter()
+        .filter(|line| line.starts_with("POST"))
+        .skip(3)
+        .collect();
+
+    assert_eq!(
+        requests,
+        vec![
+            format!("POST /v4.0.0/libpod/containers/{name}/pause"),
+            format!("POST /v4.0.0/libpod/containers/{name}/unpause"),
+            format!("POST /v4.0.0/libpod/containers/{name}/kill?signal=SIGHUP"),
+            format!("POST /v4.0.0/libpod/containers/{name}/restart?t=3"),
+            format!("POST /v4.0.0/libpod/containers/{name}/stop?timeout=3"),
+        ]
+    );
+}
+
+#[test]
+fn test_wait_for_log() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let mut container = nginx();
+
+    container.wait_for(WaitStrategy::LogMessage {
+        pattern: regex::Regex::from_str("ready for start up").unwrap(),
+    });
+
+    client.run(&container).unwrap();
+    client.wait(&container).unwrap();
+}
+
+#[test]
+fn test_network_lifecycle() {
+    let engine = fake_libpod();
+    let client = client(&engine);
+    let network = client.network(Network::new("backend"));
+
+    network.create().unwrap();
+
+    assert!(network.exists().unwrap());
+
+    network.rm().unwrap();
+
+    assert!(!network.exists().unwrap());
+}
@@ -38,3 +38,26 @@ fn test_map_exposure(#[case] client: impl Client, #[case] port: u32) {
 
     handle.stop().unwrap();
 }
+
+#[rstest]
+#[case::podman_dynamic_port(podman())]
+#[case::docker_dynamic_port(docker())]
+fn test_dynamic_port(#[case] client: impl Client) {
+    let mut container = Container::from_image(Image::from_str("docker.io/library/nginx").unwrap());
+
+    container
+        .expose_port(80)
+        .health_check(HealthCheck::new("curl http://localhost || exit 1"))
+        .wait_for(WaitStrategy::HealthCheck);
+
+    let handle = client.create(container);
+
+    handle.run().unwrap();
+    handle.wait().unwrap();
+
+    let port = handle.host_port(80.into()).unwrap().unwrap();
+
+    let response = reqwest::blocking::get(format!("http://localhost:{}", port)).unwrap();
+
+    assert!(response.status().is_success());
+}
@@ -0,0 +1,57 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::process::Command;
+
+use common::FakeReaper;
+use contain_rs_core::{
+    client::{reaper::Reaper, shared::build_run_command},
+    error::ContainersError,
+    session::{session_id, PID_LABEL, REAPER_LABEL, SESSION_LABEL},
+};
+
+#[test]
+fn test_connect_registers_session() {
+    let fake = FakeReaper::start(&["ACK"]);
+
+    let reaper = Reaper::connect(fake.address()).unwrap();
+
+    drop(reaper);
+
+    assert_eq!(
+        fake.disconnected_filter(),
+        Some(format!("label={SESSION_LABEL}={}", session_id()))
+    );
+}
+
+#[test]
+fn test_connect_rejected() {
+    let fake = FakeReaper::start(&["NOPE"]);
+
+    assert!(matches!(
+        Reaper::connect(fake.address()),
+        Err(ContainersError::ReaperFailed { .. })
+    ));
+}
+
+#[test]
+fn test_reaper_container() {
+    let container = Reaper::container("/run/podman/podman.sock");
+
+    let mut command = Command::new("podman");
+    build_run_command(&mut command, &container);
+
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+
+    assert!(args.contains(&"--rm".to_string()));
+    assert!(args.contains(&"/run/podman/podman.sock:/var/run/docker.sock".to_string()));
+    assert!(args.contains(&format!("{REAPER_LABEL}=true")));
+    assert!(args.contains(&format!("{PID_LABEL}={}", std::process::id())));
+    assert!(!args
+        .iter()
+        .any(|arg| arg.starts_with(&format!("{SESSION_LABEL}="))));
+}
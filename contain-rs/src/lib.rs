@@ -2,13 +2,25 @@
 
 pub use contain_rs_core::{
     container::{
-        Container, EnvVar, HealthCheck, Image, IntoContainer, Port, PortMapping, WaitStrategy,
+        Container, EnvVar, Exec, HealthCheck, HttpWait, Image, ImageBuild, IntoContainer, Label,
+        Network, Port, PortMapping, PullPolicy, Resources, Ulimit, WaitStrategy,
     },
     Regex,
 };
 
 pub use contain_rs_core::client::{
-    docker::Docker, podman::Podman, Client, ContainerHandle, Handle,
+    docker::Docker, docker_api::DockerApi, podman::Podman, CleanupPolicy, Client, ContainerHandle,
+    EventFilter, Events, ExecStream, Handle, NetworkHandle, Pruned,
+};
+
+pub use contain_rs_core::client::reaper::Reaper;
+
+#[cfg(unix)]
+pub use contain_rs_core::client::podman_api::PodmanApi;
+
+#[cfg(feature = "async")]
+pub use contain_rs_core::client::async_client::{
+    AsyncClient, AsyncContainerHandle, AsyncDocker, AsyncHandle, AsyncLog, AsyncPodman,
 };
 
 #[cfg(feature = "macros")]